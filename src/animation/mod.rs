@@ -0,0 +1,403 @@
+#[cfg(feature = "dev")]
+mod reload;
+mod validation;
+
+use validation::{validate_animations, AnimationConfigs};
+
+use bevy::{prelude::*, utils::hashbrown::HashMap};
+use bevy_spritesheet_animation::{
+    animation::{Animation, AnimationDirection, AnimationDuration, AnimationId, AnimationRepeat},
+    clip::{Clip, ClipId},
+    events::{AnimationEvent, AnimationMarkerId},
+    library::AnimationLibrary,
+    plugin::SpritesheetAnimationPlugin,
+    prelude::SpritesheetAnimation,
+};
+
+use crate::{
+    assets::{AnimationDefinition, EnemyArchetype, PlaybackDirection},
+    player::{DirectionChanged, MovementDirection},
+    GameState,
+};
+
+pub struct GameAnimationPlugin;
+
+impl Plugin for GameAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(SpritesheetAnimationPlugin)
+            .init_resource::<AnimationSets>()
+            .init_resource::<LoadedAnimations>()
+            .init_resource::<AnimationMarkers>()
+            .add_systems(
+                OnExit(GameState::AssetLoading),
+                (validate_animations, load_animations).chain(),
+            )
+            .add_systems(Update, (finish_one_shot_animations, raise_marker_events))
+            .observe(on_play_animation)
+            .observe(on_direction_changed);
+
+        #[cfg(feature = "dev")]
+        app.add_systems(Update, reload::reload_animations);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationState {
+    Idle,
+    Run(MovementDirection),
+    Hurt,
+    Attack,
+    Death,
+}
+
+impl AnimationState {
+    /// Hurt and attack play once, then the controller goes back to idling or running.
+    fn is_one_shot(self) -> bool {
+        matches!(self, Self::Hurt | Self::Attack)
+    }
+
+    /// States whose animation is played a single time, whatever the config says.
+    fn plays_once(self) -> bool {
+        matches!(self, Self::Hurt | Self::Attack | Self::Death)
+    }
+
+    fn is_locomotion(self) -> bool {
+        matches!(self, Self::Idle | Self::Run(_))
+    }
+}
+
+/// Animation to play in each state, states without one keep the current animation going.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationSet(HashMap<AnimationState, AnimationId>);
+
+impl AnimationSet {
+    pub fn get(&self, state: AnimationState) -> Option<AnimationId> {
+        self.0.get(&state).copied().or_else(|| match state {
+            AnimationState::Run(_) => self.0.get(&AnimationState::Idle).copied(),
+            _ => None,
+        })
+    }
+}
+
+/// Every animation set, resolved from the library once the configs are loaded.
+#[derive(Resource, Debug, Default)]
+pub struct AnimationSets {
+    /// Keyed by the `animations` prefix of a character class.
+    pub characters: HashMap<String, AnimationSet>,
+    pub enemies: HashMap<String, AnimationSet>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct LoadedAnimations {
+    /// Clip of every animation in the config, to build variants of them that only play once.
+    clips: HashMap<String, ClipId>,
+    /// Config name of every animation registered, and whether it's the play-once variant.
+    sources: HashMap<AnimationId, (String, bool)>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct AnimationMarkers(HashMap<AnimationMarkerId, String>);
+
+impl AnimationMarkers {
+    fn get_or_create(&mut self, library: &mut AnimationLibrary, name: &str) -> AnimationMarkerId {
+        if let Some((id, _)) = self.0.iter().find(|(_, marker)| *marker == name) {
+            return *id;
+        }
+
+        let id = library.new_marker();
+        self.0.insert(id, name.to_string());
+        id
+    }
+}
+
+/// Raised on an entity when its animation reaches a frame marked in the config, e.g. the frame
+/// a foot touches the ground.
+#[derive(Event, Debug)]
+pub struct AnimationMarkerHit(pub String);
+
+#[derive(Event, Debug)]
+pub struct PlayAnimation(pub AnimationState);
+
+#[derive(Component, Debug)]
+pub struct AnimationController {
+    set: AnimationSet,
+    state: AnimationState,
+    /// State to go back to once a one-shot animation is over.
+    resume: AnimationState,
+}
+
+impl AnimationController {
+    pub fn new(set: AnimationSet) -> Self {
+        Self {
+            set,
+            state: AnimationState::Idle,
+            resume: AnimationState::Idle,
+        }
+    }
+
+    pub fn initial_animation(&self) -> Option<AnimationId> {
+        self.set.get(self.state)
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    /// Dying is final, and a one-shot animation is never cut short by locomotion, which only
+    /// takes over again once it ends.
+    pub fn transition(&mut self, state: AnimationState, animation: &mut SpritesheetAnimation) {
+        if self.state == AnimationState::Death {
+            return;
+        }
+
+        if state.is_locomotion() {
+            self.resume = state;
+            if self.state.is_one_shot() {
+                return;
+            }
+        }
+
+        let Some(id) = self.set.get(state) else {
+            return;
+        };
+
+        // Several states can share an animation, no need to restart it then
+        if state.is_locomotion() && self.set.get(self.state) == Some(id) {
+            self.state = state;
+            return;
+        }
+
+        self.state = state;
+        animation.switch(id);
+    }
+}
+
+fn on_play_animation(
+    trigger: Trigger<PlayAnimation>,
+    mut query: Query<(&mut AnimationController, &mut SpritesheetAnimation)>,
+) {
+    if let Ok((mut controller, mut animation)) = query.get_mut(trigger.entity()) {
+        controller.transition(trigger.event().0, &mut animation);
+    }
+}
+
+fn on_direction_changed(
+    trigger: Trigger<DirectionChanged>,
+    mut query: Query<(&mut AnimationController, &mut SpritesheetAnimation)>,
+) {
+    let state = match trigger.event().0 {
+        MovementDirection::Idle => AnimationState::Idle,
+        direction => AnimationState::Run(direction),
+    };
+
+    if let Ok((mut controller, mut animation)) = query.get_mut(trigger.entity()) {
+        controller.transition(state, &mut animation);
+    }
+}
+
+fn finish_one_shot_animations(
+    mut events: EventReader<AnimationEvent>,
+    mut query: Query<(&mut AnimationController, &mut SpritesheetAnimation)>,
+) {
+    for event in events.read() {
+        let AnimationEvent::AnimationEnd { entity, .. } = event else {
+            continue;
+        };
+
+        let Ok((mut controller, mut animation)) = query.get_mut(*entity) else {
+            continue;
+        };
+
+        if controller.state.is_one_shot() {
+            controller.state = controller.resume;
+            if let Some(id) = controller.set.get(controller.state) {
+                animation.switch(id);
+            }
+        }
+    }
+}
+
+fn raise_marker_events(
+    mut commands: Commands,
+    mut events: EventReader<AnimationEvent>,
+    markers: Res<AnimationMarkers>,
+) {
+    for event in events.read() {
+        if let AnimationEvent::MarkerHit {
+            entity, marker_id, ..
+        } = event
+        {
+            if let Some(name) = markers.0.get(marker_id) {
+                commands.trigger_targets(AnimationMarkerHit(name.clone()), *entity);
+            }
+        }
+    }
+}
+
+fn running_suffix(direction: MovementDirection) -> Option<&'static str> {
+    match direction {
+        MovementDirection::Up => Some("up"),
+        MovementDirection::UpLeft => Some("up_left"),
+        MovementDirection::Left => Some("left"),
+        MovementDirection::DownLeft => Some("down_left"),
+        MovementDirection::Down => Some("down"),
+        MovementDirection::DownRight => Some("down_right"),
+        MovementDirection::Right => Some("right"),
+        MovementDirection::RightUp => Some("up_right"),
+        MovementDirection::Idle => None,
+    }
+}
+
+/// Every animation a character class can play, with whether the config has to define it.
+fn character_animations(prefix: &str) -> Vec<(AnimationState, String, bool)> {
+    let mut animations = vec![(AnimationState::Idle, format!("{prefix}_idle"), true)];
+    for direction in MovementDirection::ALL {
+        if let Some(suffix) = running_suffix(direction) {
+            let name = format!("{prefix}_running_{suffix}");
+            animations.push((AnimationState::Run(direction), name, true));
+        }
+    }
+    animations.extend([
+        (AnimationState::Hurt, format!("{prefix}_hurt"), false),
+        (AnimationState::Attack, format!("{prefix}_attack"), false),
+        (AnimationState::Death, format!("{prefix}_death"), false),
+    ]);
+    animations
+}
+
+fn enemy_animations(archetype: &EnemyArchetype) -> Vec<(AnimationState, String, bool)> {
+    vec![
+        (AnimationState::Idle, archetype.walk_animation.clone(), true),
+        (
+            AnimationState::Death,
+            archetype.death_animation.clone(),
+            true,
+        ),
+    ]
+}
+
+struct BuiltAnimations {
+    library: AnimationLibrary,
+    loaded: LoadedAnimations,
+    markers: AnimationMarkers,
+    sets: AnimationSets,
+}
+
+impl BuiltAnimations {
+    fn build(configs: &AnimationConfigs) -> Option<Self> {
+        let (Some(animations), Some(characters), Some(enemies)) = (
+            configs.animations(),
+            configs.characters(),
+            configs.enemies(),
+        ) else {
+            return None;
+        };
+
+        let mut built = Self {
+            library: AnimationLibrary::default(),
+            loaded: LoadedAnimations::default(),
+            markers: AnimationMarkers::default(),
+            sets: AnimationSets::default(),
+        };
+
+        for (animation_name, definition) in animations.0.values().flatten() {
+            let (clip, repeat) = build_clip(&mut built.library, &mut built.markers, definition);
+            let clip_id = built.library.register_clip(clip);
+            let animation = Animation::from_clip(clip_id).with_repetitions(repeat);
+            let animation_id = built.library.register_animation(animation);
+            // Collisions were already reported by the validation pass
+            let _ = built.library.name_animation(animation_id, animation_name);
+            built.loaded.clips.insert(animation_name.clone(), clip_id);
+            built
+                .loaded
+                .sources
+                .insert(animation_id, (animation_name.clone(), false));
+        }
+
+        for class in characters.0.values() {
+            let set = built.resolve_set(character_animations(&class.animations));
+            built.sets.characters.insert(class.animations.clone(), set);
+        }
+
+        for (name, archetype) in &enemies.0 {
+            let set = built.resolve_set(enemy_animations(archetype));
+            built.sets.enemies.insert(name.clone(), set);
+        }
+
+        Some(built)
+    }
+
+    fn resolve_set(&mut self, animations: Vec<(AnimationState, String, bool)>) -> AnimationSet {
+        let mut set = AnimationSet::default();
+        for (state, name, required) in animations {
+            let id = if state.plays_once() {
+                // Enemies may die to their walk cycle, so play-once states get their own animation
+                self.loaded.clips.get(&name).map(|clip| {
+                    let animation =
+                        Animation::from_clip(*clip).with_repetitions(AnimationRepeat::Times(1));
+                    let id = self.library.register_animation(animation);
+                    self.loaded.sources.insert(id, (name.clone(), true));
+                    id
+                })
+            } else {
+                self.library.animation_with_name(&name)
+            };
+
+            match id {
+                Some(id) => {
+                    set.0.insert(state, id);
+                }
+                // Optional states fall back to idling, a required one missing is a broken config
+                None if required => error!("Missing required animation {name} for {state:?}"),
+                None => {}
+            }
+        }
+        set
+    }
+
+    fn insert(self, commands: &mut Commands) {
+        commands.insert_resource(self.library);
+        commands.insert_resource(self.loaded);
+        commands.insert_resource(self.markers);
+        commands.insert_resource(self.sets);
+    }
+}
+
+fn load_animations(mut commands: Commands, configs: AnimationConfigs) {
+    if let Some(built) = BuiltAnimations::build(&configs) {
+        built.insert(&mut commands);
+    }
+}
+
+fn build_clip(
+    library: &mut AnimationLibrary,
+    markers: &mut AnimationMarkers,
+    definition: &AnimationDefinition,
+) -> (Clip, AnimationRepeat) {
+    let mut clip = Clip::from_frames(definition.frames().iter().copied());
+    let AnimationDefinition::Detailed(detailed) = definition else {
+        return (clip, AnimationRepeat::Loop);
+    };
+
+    if let Some(fps) = detailed.fps {
+        clip = clip.with_duration(AnimationDuration::PerFrame((1000.0 / fps) as u32));
+    } else if let Some(duration_ms) = detailed.duration_ms {
+        clip = clip.with_duration(AnimationDuration::PerRepetition(duration_ms));
+    }
+
+    clip = clip.with_direction(match detailed.direction {
+        PlaybackDirection::Forwards => AnimationDirection::Forwards,
+        PlaybackDirection::Backwards => AnimationDirection::Backwards,
+        PlaybackDirection::PingPong => AnimationDirection::PingPong,
+    });
+
+    for (name, frame) in &detailed.markers {
+        let marker = markers.get_or_create(library, name);
+        clip.add_marker(marker, *frame);
+    }
+
+    let repeat = detailed
+        .repetitions
+        .map_or(AnimationRepeat::Loop, AnimationRepeat::Times);
+    (clip, repeat)
+}
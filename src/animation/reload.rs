@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_spritesheet_animation::{animation::AnimationId, prelude::SpritesheetAnimation};
+
+use crate::assets::{AnimationsConfig, CharactersConfig, EnemiesConfig};
+
+use super::{AnimationConfigs, AnimationController, BuiltAnimations, LoadedAnimations};
+
+/// Rebuilds the animation library whenever a config it's built from changes on disk, and moves
+/// every live animation over to it. An invalid config keeps the previous animations around.
+pub(super) fn reload_animations(
+    mut commands: Commands,
+    mut animation_events: EventReader<AssetEvent<AnimationsConfig>>,
+    mut character_events: EventReader<AssetEvent<CharactersConfig>>,
+    mut enemy_events: EventReader<AssetEvent<EnemiesConfig>>,
+    configs: AnimationConfigs,
+    previous: Res<LoadedAnimations>,
+    mut animated: Query<(&mut SpritesheetAnimation, Option<&mut AnimationController>)>,
+) {
+    // Every reader is drained, an event left unread would trigger a second reload next frame
+    let modified = animation_events.read().filter(is_modified).count()
+        + character_events.read().filter(is_modified).count()
+        + enemy_events.read().filter(is_modified).count();
+    if modified == 0 {
+        return;
+    }
+
+    let problems = configs.problems();
+    if !problems.is_empty() {
+        return error!(
+            "Keeping the previous animations, the new config is invalid:\n  {}",
+            problems.join("\n  ")
+        );
+    }
+
+    let Some(built) = BuiltAnimations::build(&configs) else {
+        return;
+    };
+
+    for (mut animation, controller) in &mut animated {
+        if let Some(mut controller) = controller {
+            controller.set.0 = controller
+                .set
+                .0
+                .iter()
+                .filter_map(|(state, id)| Some((*state, built.remap(&previous, *id)?)))
+                .collect();
+        }
+
+        if let Some(id) = built.remap(&previous, animation.animation_id) {
+            animation.switch(id);
+        }
+    }
+
+    built.insert(&mut commands);
+    info!("Reloaded animations");
+}
+
+fn is_modified<A: Asset>(event: &&AssetEvent<A>) -> bool {
+    matches!(event, AssetEvent::Modified { .. })
+}
+
+impl BuiltAnimations {
+    fn remap(&self, previous: &LoadedAnimations, id: AnimationId) -> Option<AnimationId> {
+        let source = previous.sources.get(&id)?;
+        self.loaded
+            .sources
+            .iter()
+            .find_map(|(id, other)| (other == source).then_some(*id))
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::hashbrown::HashMap};
+
+use crate::{
+    assets::{
+        AnimationDefinition, AnimationsConfig, CharactersConfig, ConfigHandles, EnemiesConfig,
+        GameAssetsHandles,
+    },
+    campfire::CAMPFIRE_ANIMATION,
+    resources::{Resource, CHEST_ANIMATION},
+};
+
+use super::{character_animations, enemy_animations};
+
+struct Reference {
+    name: String,
+    user: String,
+    layout: Option<(String, Handle<TextureAtlasLayout>)>,
+    required: bool,
+}
+
+/// Checks the animations config against everything using it before the library gets built,
+/// and exits with every problem found rather than failing on the first one mid-run.
+pub(super) fn validate_animations(configs: AnimationConfigs, mut exit: EventWriter<AppExit>) {
+    let problems = configs.problems();
+    if !problems.is_empty() {
+        error!("Invalid animation config:\n  {}", problems.join("\n  "));
+        exit.send(AppExit::error());
+    }
+}
+
+#[derive(SystemParam)]
+pub(super) struct AnimationConfigs<'w> {
+    config_handles: Res<'w, ConfigHandles>,
+    handles: Res<'w, GameAssetsHandles>,
+    animations: Res<'w, Assets<AnimationsConfig>>,
+    characters: Res<'w, Assets<CharactersConfig>>,
+    enemies: Res<'w, Assets<EnemiesConfig>>,
+    layouts: Res<'w, Assets<TextureAtlasLayout>>,
+}
+
+impl AnimationConfigs<'_> {
+    pub(super) fn animations(&self) -> Option<&AnimationsConfig> {
+        self.animations.get(self.config_handles.animations.id())
+    }
+
+    pub(super) fn characters(&self) -> Option<&CharactersConfig> {
+        self.characters.get(self.config_handles.characters.id())
+    }
+
+    pub(super) fn enemies(&self) -> Option<&EnemiesConfig> {
+        self.enemies.get(self.config_handles.enemies.id())
+    }
+
+    pub(super) fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let handles = &self.handles;
+
+        let Some(animations) = self.animations() else {
+            return vec!["config.animations.ron isn't loaded".to_string()];
+        };
+
+        let mut groups = animations.0.iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(group, _)| *group);
+
+        let mut owners = HashMap::<&str, &str>::new();
+        for (group, clips) in &groups {
+            let mut clips = clips.iter().collect::<Vec<_>>();
+            clips.sort_by_key(|(name, _)| *name);
+            for (name, definition) in clips {
+                if let Some(other) = owners.insert(name, group) {
+                    problems.push(format!(
+                        "`{name}` is defined in both `{other}` and `{group}`"
+                    ));
+                }
+                check_definition(name, definition, &mut problems);
+            }
+        }
+
+        let mut references = Vec::new();
+
+        match self.characters() {
+            Some(characters) => {
+                for (class_name, class) in &characters.0 {
+                    for (_, name, required) in character_animations(&class.animations) {
+                        references.push(Reference {
+                            name,
+                            user: format!("character `{class_name}`"),
+                            layout: Some((
+                                "characters_layouts".to_string(),
+                                handles.characters_layouts.clone(),
+                            )),
+                            required,
+                        });
+                    }
+                }
+            }
+            None => problems.push("config.characters.ron isn't loaded".to_string()),
+        }
+
+        match self.enemies() {
+            Some(enemies) => {
+                for (archetype_name, archetype) in &enemies.0 {
+                    let layout = handles.get_field::<Handle<TextureAtlasLayout>>(&archetype.layout);
+                    if layout.is_none() {
+                        problems.push(format!(
+                            "enemy `{archetype_name}` uses unknown layout `{}`",
+                            archetype.layout
+                        ));
+                    }
+
+                    for (_, name, required) in enemy_animations(archetype) {
+                        references.push(Reference {
+                            name,
+                            user: format!("enemy `{archetype_name}`"),
+                            layout: layout.map(|layout| (archetype.layout.clone(), layout.clone())),
+                            required,
+                        });
+                    }
+                }
+            }
+            None => problems.push("config.enemies.ron isn't loaded".to_string()),
+        }
+
+        for resource in Resource::ALL {
+            references.push(Reference {
+                name: resource.animation_name().to_string(),
+                user: format!("resource {resource:?}"),
+                layout: Some((
+                    "resources_layout".to_string(),
+                    handles.resources_layout.clone(),
+                )),
+                required: true,
+            });
+        }
+
+        references.push(Reference {
+            name: CHEST_ANIMATION.to_string(),
+            user: "chest".to_string(),
+            layout: Some((
+                "resources_layout".to_string(),
+                handles.resources_layout.clone(),
+            )),
+            required: true,
+        });
+        references.push(Reference {
+            name: CAMPFIRE_ANIMATION.to_string(),
+            user: "campfire".to_string(),
+            layout: Some((
+                "resources_layout".to_string(),
+                handles.resources_layout.clone(),
+            )),
+            required: true,
+        });
+
+        references.sort_by(|a, b| (&a.user, &a.name).cmp(&(&b.user, &b.name)));
+
+        // A group is drawn from the atlas of whoever uses its animations, so its frames have to fit
+        let mut group_layouts = HashMap::<&str, Vec<(String, Handle<TextureAtlasLayout>)>>::new();
+        for reference in &references {
+            match owners.get(reference.name.as_str()) {
+                Some(group) => {
+                    let Some(layout) = &reference.layout else {
+                        continue;
+                    };
+                    let group_layouts = group_layouts.entry(*group).or_default();
+                    if !group_layouts.iter().any(|(name, _)| *name == layout.0) {
+                        group_layouts.push(layout.clone());
+                    }
+                }
+                None if reference.required => problems.push(format!(
+                    "{} uses `{}`, which isn't defined",
+                    reference.user, reference.name
+                )),
+                None => {}
+            }
+        }
+
+        for (group, clips) in &groups {
+            let Some(group_layouts) = group_layouts.get(group.as_str()) else {
+                continue;
+            };
+
+            let mut clips = clips.iter().collect::<Vec<_>>();
+            clips.sort_by_key(|(name, _)| *name);
+            for (layout_name, handle) in group_layouts {
+                // A layout that failed to load leaves nothing to check the frames against
+                let Some(layout) = self.layouts.get(handle) else {
+                    continue;
+                };
+                let frame_count = layout.textures.len();
+
+                for (name, definition) in &clips {
+                    let frames = definition.frames();
+                    for frame in frames.iter().filter(|frame| **frame >= frame_count) {
+                        problems.push(format!(
+                        "`{name}` uses frame {frame}, but `{layout_name}` only has {frame_count}"
+                    ));
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+fn check_definition(name: &str, definition: &AnimationDefinition, problems: &mut Vec<String>) {
+    if definition.frames().is_empty() {
+        problems.push(format!("`{name}` has no frames"));
+    }
+
+    let AnimationDefinition::Detailed(detailed) = definition else {
+        return;
+    };
+
+    if detailed.fps.is_some() && detailed.duration_ms.is_some() {
+        problems.push(format!("`{name}` sets both `fps` and `duration_ms`"));
+    }
+    if detailed.fps.is_some_and(|fps| fps <= 0.0) {
+        problems.push(format!("`{name}` needs a positive `fps`"));
+    }
+    if detailed.repetitions == Some(0) {
+        problems.push(format!("`{name}` never plays with 0 `repetitions`"));
+    }
+
+    let mut markers = detailed.markers.iter().collect::<Vec<_>>();
+    markers.sort();
+    for (marker, frame) in markers {
+        if *frame >= detailed.frames.len() {
+            problems.push(format!(
+                "`{name}` marks `{marker}` on frame {frame}, but only has {} frames",
+                detailed.frames.len()
+            ));
+        }
+    }
+}
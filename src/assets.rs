@@ -3,28 +3,388 @@ use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy_asset_loader::prelude::*;
 
 use bevy_common_assets::ron::RonAssetPlugin;
+use rand::{seq::SliceRandom, Rng};
 
-use crate::GameState;
+use crate::{
+    player::Weapon,
+    resources::{Loot, Resource},
+    stats::StatModifier,
+    GameState,
+};
+
+pub struct ConfigAssetsPlugin;
+
+impl Plugin for ConfigAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RonAssetPlugin::<AnimationsConfig>::new(&["animations.ron"]),
+            RonAssetPlugin::<EnemiesConfig>::new(&["enemies.ron"]),
+            RonAssetPlugin::<WavesConfig>::new(&["waves.ron"]),
+            RonAssetPlugin::<ProgressionConfig>::new(&["progression.ron"]),
+            RonAssetPlugin::<CharactersConfig>::new(&["characters.ron"]),
+            RonAssetPlugin::<ShopConfig>::new(&["shop.ron"]),
+            RonAssetPlugin::<DropsConfig>::new(&["drops.ron"]),
+            RonAssetPlugin::<CampfireConfig>::new(&["campfires.ron"]),
+            RonAssetPlugin::<TerrainConfig>::new(&["terrain.ron"]),
+        ));
+    }
+}
 
 pub struct GameAssetsPlugin;
 
 impl Plugin for GameAssetsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((RonAssetPlugin::<AnimationsConfig>::new(&["animations.ron"]),))
-            .add_loading_state(
-                LoadingState::new(GameState::AssetLoading)
-                    .continue_to_state(GameState::Next)
-                    .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                        "characters.assets.ron",
-                    )
-                    .load_collection::<ConfigHandles>()
-                    .load_collection::<GameAssetsHandles>(),
-            );
+        app.add_loading_state(
+            LoadingState::new(GameState::AssetLoading)
+                .continue_to_state(GameState::MainMenu)
+                .with_dynamic_assets_file::<StandardDynamicAssetCollection>("characters.assets.ron")
+                .load_collection::<ConfigHandles>()
+                .load_collection::<GameAssetsHandles>(),
+        );
+    }
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct AnimationsConfig(pub HashMap<String, HashMap<String, AnimationDefinition>>);
+
+/// Either a bare list of frames, looping at the default rate, or a full description of the clip.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum AnimationDefinition {
+    Frames(Vec<usize>),
+    Detailed(DetailedAnimation),
+}
+
+impl AnimationDefinition {
+    pub fn frames(&self) -> &[usize] {
+        match self {
+            AnimationDefinition::Frames(frames) => frames,
+            AnimationDefinition::Detailed(animation) => &animation.frames,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct DetailedAnimation {
+    pub frames: Vec<usize>,
+    /// Frames per second, exclusive with `duration_ms`.
+    #[serde(default)]
+    pub fps: Option<f32>,
+    /// Length of a single play through all the frames.
+    #[serde(default)]
+    pub duration_ms: Option<u32>,
+    /// How many times the animation plays, forever when left out.
+    #[serde(default)]
+    pub repetitions: Option<u32>,
+    #[serde(default)]
+    pub direction: PlaybackDirection,
+    /// Marker name to the index, within `frames`, of the frame raising it.
+    #[serde(default)]
+    pub markers: HashMap<String, usize>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default)]
+pub enum PlaybackDirection {
+    #[default]
+    Forwards,
+    Backwards,
+    PingPong,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct EnemiesConfig(pub HashMap<String, EnemyArchetype>);
+
+impl EnemiesConfig {
+    pub fn choose<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(&String, &EnemyArchetype)> {
+        // Map iteration order changes between runs, sort so a seeded rng always picks the same
+        let mut archetypes = self.0.iter().collect::<Vec<_>>();
+        archetypes.sort_by_key(|(name, _)| *name);
+        archetypes
+            .choose_weighted(rng, |(_, archetype)| archetype.spawn_weight)
+            .ok()
+            .copied()
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct EnemyArchetype {
+    pub sheet: String,
+    pub layout: String,
+    pub speed: f32,
+    pub health: u32,
+    pub contact_damage: u32,
+    pub collider: (f32, f32),
+    pub walk_animation: String,
+    pub death_animation: String,
+    pub spawn_weight: u32,
+    /// Enemies stop walking once this close to the player.
+    #[serde(default = "EnemyArchetype::default_stop_distance")]
+    pub stop_distance: f32,
+    /// Name of the `DropsConfig` table rolled on death.
+    pub drops: String,
+    /// Table rolled instead of `drops` when an elite of this archetype dies.
+    #[serde(default)]
+    pub elite_drops: Option<String>,
+}
+
+impl EnemyArchetype {
+    fn default_stop_distance() -> f32 {
+        10.0
+    }
+
+    pub fn drop_table(&self, elite: bool) -> &str {
+        match &self.elite_drops {
+            Some(elite_drops) if elite => elite_drops,
+            _ => &self.drops,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct DropsConfig(pub HashMap<String, DropTable>);
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct DropTable {
+    /// Dropped every time, on top of the weighted pick.
+    #[serde(default)]
+    pub guaranteed: Vec<DropEntry>,
+    /// At most one of these is dropped, picked by weight.
+    #[serde(default)]
+    pub weighted: Vec<DropEntry>,
+    /// Weight of the weighted pick coming up empty, divided by `1.0` plus the killer's `Stat::Luck`.
+    #[serde(default)]
+    pub nothing: f32,
+}
+
+impl DropTable {
+    pub fn roll<R: Rng + ?Sized>(&self, luck: f32, rng: &mut R) -> Vec<(Loot, u32)> {
+        let nothing = self.nothing / (1.0 + luck.max(0.0));
+        let picked = self
+            .weighted
+            .iter()
+            .map(Some)
+            .chain([None])
+            .collect::<Vec<_>>()
+            .choose_weighted(rng, |entry| entry.map_or(nothing, |entry| entry.weight))
+            .ok()
+            .copied()
+            .flatten();
+
+        self.guaranteed
+            .iter()
+            .chain(picked)
+            .map(|entry| {
+                let (min, max) = entry.amount;
+                (entry.loot.clone(), rng.gen_range(min..=max.max(min)))
+            })
+            .collect()
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct DropEntry {
+    pub loot: Loot,
+    /// Ignored for guaranteed drops.
+    #[serde(default = "DropEntry::default_weight")]
+    pub weight: f32,
+    /// Inclusive range the dropped amount is picked from.
+    #[serde(default = "DropEntry::default_amount")]
+    pub amount: (u32, u32),
+}
+
+impl DropEntry {
+    fn default_weight() -> f32 {
+        1.0
+    }
+
+    fn default_amount() -> (u32, u32) {
+        (1, 1)
+    }
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct CampfireConfig {
+    /// Seconds between two campfires appearing.
+    pub interval: f32,
+    /// Range of the distance from the player a campfire appears at.
+    pub distance: (f32, f32),
+    /// No new campfire appears while this many are left.
+    pub max_active: usize,
+    /// Campfires further than this from the player burn out.
+    pub despawn_distance: f32,
+    /// How close the player has to stay while resting.
+    pub radius: f32,
+    /// Seconds of resting before the player is healed and the shop opens.
+    pub rest_time: f32,
+    /// Fraction of the max health healed by a completed rest.
+    pub heal: f32,
+    pub buffs: Vec<CampfireBuff>,
+    /// Price of raising an equipped weapon by one level.
+    pub weapon_level_cost: Vec<(Resource, u32)>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct CampfireBuff {
+    pub modifier: StatModifier,
+    /// Seconds the modifier stays applied.
+    pub duration: f32,
+    pub cost: Vec<(Resource, u32)>,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct TerrainConfig {
+    pub tile_size: f32,
+    /// Tiles along each side of a chunk.
+    pub chunk_tiles: u32,
+    /// Chunks kept loaded on every side of the one the player stands in.
+    pub load_radius: i32,
+    /// Chunks further than this get despawned, above `load_radius` so walking back and forth
+    /// over a chunk border doesn't keep reloading the same chunks.
+    pub unload_radius: i32,
+    /// Size in tiles of the patches the ground colors form.
+    pub patch_size: f32,
+    /// sRGB ground colors, picked by the noise value from low to high.
+    pub ground: Vec<(f32, f32, f32)>,
+    pub props: Vec<PropDefinition>,
+    /// No obstacle is placed this close to where runs start.
+    pub clear_radius: f32,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PropDefinition {
+    pub size: (f32, f32),
+    pub color: (f32, f32, f32),
+    /// Average number of these in a chunk.
+    pub per_chunk: f32,
+    /// Neither the player nor enemies can walk through obstacles.
+    #[serde(default)]
+    pub obstacle: bool,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct WavesConfig {
+    pub waves: Vec<WaveDefinition>,
+    #[serde(default)]
+    pub events: Vec<ScriptedEvent>,
+}
+
+impl WavesConfig {
+    pub fn wave_at(&self, elapsed: f32) -> Option<usize> {
+        self.waves.iter().rposition(|wave| wave.start <= elapsed)
     }
 }
 
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WaveDefinition {
+    pub start: f32,
+    pub spawn_interval: f32,
+    pub group_size: (u32, u32),
+    /// Spawn weights overriding the ones from `EnemiesConfig`, empty means use the defaults.
+    #[serde(default)]
+    pub archetypes: HashMap<String, u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ScriptedEvent {
+    pub at: f32,
+    pub kind: ScriptedEventKind,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub enum ScriptedEventKind {
+    Swarm {
+        archetype: String,
+        count: u32,
+    },
+    Ring {
+        archetype: String,
+        count: u32,
+        radius: f32,
+    },
+    Elite {
+        archetype: String,
+        count: u32,
+        health_multiplier: f32,
+    },
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct CharactersConfig(pub HashMap<String, CharacterClass>);
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct CharacterClass {
+    pub title: String,
+    pub sheet: String,
+    /// Index into `portraits_layout`.
+    pub portrait: usize,
+    pub starting_weapon: Weapon,
+    pub speed: f32,
+    pub health: u32,
+    pub passive: StatModifier,
+    /// Prefix of the class animation names in `AnimationsConfig`, e.g. `player` for
+    /// `player_idle`.
+    pub animations: String,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Debug)]
+pub struct ShopConfig(pub HashMap<String, ShopUpgrade>);
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ShopUpgrade {
+    pub title: String,
+    pub effect: ShopEffect,
+    pub max_level: u32,
+    /// Price of the first level, every level after it costs that much more.
+    pub cost: Vec<(Resource, u32)>,
+}
+
+impl ShopUpgrade {
+    pub fn cost_at(&self, level: u32) -> Vec<(Resource, u32)> {
+        self.cost
+            .iter()
+            .map(|(resource, amount)| (*resource, amount * (level + 1)))
+            .collect()
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+pub enum ShopEffect {
+    Stat(StatModifier),
+    /// Raises the level of the starting weapon.
+    StartingWeaponLevel,
+    /// Lets the offered upgrades be rolled again once per run on any level up.
+    Reroll,
+}
+
 #[derive(serde::Deserialize, Asset, TypePath, Debug)]
-pub struct AnimationsConfig(pub HashMap<String, HashMap<String, Vec<usize>>>);
+pub struct ProgressionConfig {
+    pub experience_curve: ExperienceCurve,
+    pub gem_value: u32,
+    pub elite_gem_value: u32,
+    pub upgrade_choices: usize,
+    pub stat_boosts: Vec<StatModifier>,
+    pub chest: ChestRewards,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ChestRewards {
+    /// Chance of granting upgrades instead of rolling the chest's drop table.
+    pub upgrade_chance: f32,
+    /// Weights of granting one, two, three and so on upgrades at once.
+    pub upgrade_counts: Vec<u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ExperienceCurve {
+    pub base: f32,
+    pub growth: f32,
+}
+
+impl ExperienceCurve {
+    pub fn threshold(&self, level: u32) -> u32 {
+        (self.base * self.growth.powi(level.saturating_sub(1) as i32)).round() as u32
+    }
+}
 
 #[derive(AssetCollection, Resource, Asset, Reflect)]
 pub struct GameAssetsHandles {
@@ -34,6 +394,8 @@ pub struct GameAssetsHandles {
     pub characters_sheets: HashMap<String, Handle<Image>>,
     #[asset(key = "characters.portrait")]
     pub portraits: Handle<Image>,
+    #[asset(key = "characters.portraits_layout")]
+    pub portraits_layout: Handle<TextureAtlasLayout>,
     #[asset(key = "monsters.halfling.texture_atlas_layout")]
     pub halfling_layout: Handle<TextureAtlasLayout>,
     #[asset(key = "monster.skeleton.texture_atlas_layout")]
@@ -66,4 +428,108 @@ impl GameAssetsHandles {
 pub struct ConfigHandles {
     #[asset(path = "config.animations.ron")]
     pub animations: Handle<AnimationsConfig>,
+    #[asset(path = "config.enemies.ron")]
+    pub enemies: Handle<EnemiesConfig>,
+    #[asset(path = "config.waves.ron")]
+    pub waves: Handle<WavesConfig>,
+    #[asset(path = "config.progression.ron")]
+    pub progression: Handle<ProgressionConfig>,
+    #[asset(path = "config.characters.ron")]
+    pub characters: Handle<CharactersConfig>,
+    #[asset(path = "config.shop.ron")]
+    pub shop: Handle<ShopConfig>,
+    #[asset(path = "config.drops.ron")]
+    pub drops: Handle<DropsConfig>,
+    #[asset(path = "config.campfires.ron")]
+    pub campfires: Handle<CampfireConfig>,
+    #[asset(path = "config.terrain.ron")]
+    pub terrain: Handle<TerrainConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_rand::prelude::ChaCha8Rng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn entry(resource: Resource, weight: f32, amount: (u32, u32)) -> DropEntry {
+        DropEntry {
+            loot: Loot::Resource(resource),
+            weight,
+            amount,
+        }
+    }
+
+    #[test]
+    fn guaranteed_drops_always_come_with_at_most_one_weighted_pick() {
+        let table = DropTable {
+            guaranteed: vec![entry(Resource::Gold, 1.0, (2, 4))],
+            weighted: vec![
+                entry(Resource::Wood, 1.0, (1, 1)),
+                entry(Resource::Ore, 1.0, (1, 1)),
+            ],
+            nothing: 1.0,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for _ in 0..200 {
+            let drops = table.roll(0.0, &mut rng);
+            assert!(drops.len() == 1 || drops.len() == 2);
+            let (loot, amount) = &drops[0];
+            assert_eq!(*loot, Loot::Resource(Resource::Gold));
+            assert!((2..=4).contains(amount));
+        }
+    }
+
+    #[test]
+    fn luck_makes_empty_rolls_rarer() {
+        let table = DropTable {
+            guaranteed: Vec::new(),
+            weighted: vec![entry(Resource::Gold, 1.0, (1, 1))],
+            nothing: 1.0,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut empty = |luck| {
+            (0..1000)
+                .filter(|_| table.roll(luck, &mut rng).is_empty())
+                .count()
+        };
+
+        let unlucky = empty(0.0);
+        let lucky = empty(1.0);
+        assert!((400..600).contains(&unlucky));
+        assert!((250..420).contains(&lucky));
+    }
+
+    #[test]
+    fn tables_without_a_nothing_weight_always_drop() {
+        let table = DropTable {
+            guaranteed: Vec::new(),
+            weighted: vec![entry(Resource::Gold, 1.0, (3, 3))],
+            nothing: 0.0,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            assert_eq!(
+                table.roll(0.0, &mut rng),
+                vec![(Loot::Resource(Resource::Gold), 3)]
+            );
+        }
+    }
+
+    #[test]
+    fn experience_threshold_grows_from_the_base() {
+        let curve = ExperienceCurve {
+            base: 5.0,
+            growth: 1.5,
+        };
+
+        assert_eq!(curve.threshold(0), 5);
+        assert_eq!(curve.threshold(1), 5);
+        assert_eq!(curve.threshold(2), 8);
+        assert_eq!(curve.threshold(3), 11);
+        assert!(curve.threshold(10) > curve.threshold(9));
+    }
 }
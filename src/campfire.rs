@@ -0,0 +1,536 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use bevy_spritesheet_animation::{component::SpritesheetAnimation, library::AnimationLibrary};
+use rand::Rng;
+
+use crate::{
+    assets::{CampfireConfig, ConfigHandles, GameAssetsHandles},
+    common::{Health, MaxHealth},
+    input::{CampfireChoice, InputSource},
+    menu::BUTTON_COLOR,
+    player::{Player, PlayerHit, UpgradeWeapon, Weapon, WeaponLevel, MAX_WEAPON_LEVEL},
+    resources::{Resource, Resources},
+    run::InRun,
+    stats::{StatModifier, StatModifiers, StatsSystems},
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    terrain::LoadedChunks,
+    GameState,
+};
+
+pub const CAMPFIRE_ANIMATION: &str = "campfire";
+/// Spots tried around the player before giving up on a campfire until the next interval.
+const SPAWN_ATTEMPTS: usize = 8;
+
+pub struct CampfirePlugin;
+
+impl Plugin for CampfirePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CampfireTimer>()
+            .add_systems(OnEnter(InRun), reset_campfire_timer)
+            .add_systems(
+                FixedUpdate,
+                (
+                    spawn_campfires,
+                    rest_at_campfires,
+                    expire_buffs.before(StatsSystems),
+                )
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            )
+            .add_systems(OnEnter(GameState::Campfire), spawn_campfire_shop)
+            .add_systems(
+                Update,
+                (choose_at_campfire, refresh_campfire_shop)
+                    .chain()
+                    .run_if(in_state(GameState::Campfire)),
+            )
+            .add_systems(Update, capture_campfires.in_set(SuspendSystems::Capture))
+            .add_systems(
+                FixedFirst,
+                restore_campfires.in_set(SuspendSystems::Restore),
+            )
+            .observe(add_temporary_buffs);
+    }
+}
+
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+struct CampfireTimer(Timer);
+
+#[derive(Component)]
+struct Campfire;
+
+/// Present on the player while standing by a campfire, taking a hit or walking away cancels it.
+#[derive(Component, Debug)]
+struct Resting {
+    campfire: Entity,
+    timer: Timer,
+}
+
+/// Campfire buffs still active on the player, their modifiers are taken back once they run out.
+#[derive(Component, Debug, Default)]
+struct TemporaryBuffs(Vec<TemporaryBuff>);
+
+#[derive(Debug)]
+struct TemporaryBuff {
+    modifier: StatModifier,
+    timer: Timer,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct CampfireSnapshot {
+    pub elapsed: f32,
+    pub positions: Vec<Vec2>,
+    /// Every active buff along with its remaining seconds.
+    pub buffs: Vec<(StatModifier, f32)>,
+}
+
+#[derive(Component)]
+struct CampfireShop;
+
+#[derive(Component)]
+struct CampfireButton(CampfireChoice);
+
+fn reset_campfire_timer(
+    mut timer: ResMut<CampfireTimer>,
+    config_handles: Res<ConfigHandles>,
+    campfires: Res<Assets<CampfireConfig>>,
+) {
+    let Some(config) = campfires.get(config_handles.campfires.id()) else {
+        return error!("Campfire config should be loaded at this point");
+    };
+    timer.0 = Timer::from_seconds(config.interval, TimerMode::Repeating);
+}
+
+fn add_temporary_buffs(trigger: Trigger<OnAdd, Player>, mut commands: Commands) {
+    commands
+        .entity(trigger.entity())
+        .insert(TemporaryBuffs::default());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_campfires(
+    mut commands: Commands,
+    mut timer: ResMut<CampfireTimer>,
+    player: Query<&Transform, With<Player>>,
+    campfires: Query<(Entity, &Transform), With<Campfire>>,
+    chunks: Res<LoadedChunks>,
+    config_handles: Res<ConfigHandles>,
+    campfires: Res<Assets<CampfireConfig>>,
+    handles: Res<GameAssetsHandles>,
+    animations: Res<AnimationLibrary>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    time: Res<Time>,
+) {
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some(config) = campfires.get(config_handles.campfires.id()) else {
+        return error!("Campfire config should be loaded at this point");
+    };
+    let Ok(transform) = player.get_single() else {
+        return;
+    };
+
+    let player = transform.translation.truncate();
+
+    let mut active = 0;
+    for (entity, campfire) in &campfires {
+        if campfire.translation.truncate().distance(player) > config.despawn_distance {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            active += 1;
+        }
+    }
+    if active >= config.max_active {
+        return;
+    }
+
+    // The whole resting area has to be clear of obstacles
+    let (min, max) = config.distance;
+    for _ in 0..SPAWN_ATTEMPTS {
+        let angle = rng.gen_range(0.0..TAU);
+        let distance = rng.gen_range(min..=max.max(min));
+        let position = player + Vec2::from_angle(angle) * distance;
+
+        let blocked = chunks
+            .obstacles_near(position, config.radius)
+            .any(|obstacle| obstacle.center.distance(position) < obstacle.radius + config.radius);
+        if !blocked {
+            return spawn_campfire(&mut commands, position, &handles, &animations);
+        }
+    }
+}
+
+fn spawn_campfire(
+    commands: &mut Commands,
+    position: Vec2,
+    handles: &GameAssetsHandles,
+    animations: &AnimationLibrary,
+) {
+    let Some(animation_id) = animations.animation_with_name(CAMPFIRE_ANIMATION) else {
+        return error!("Missing {CAMPFIRE_ANIMATION} animation");
+    };
+
+    commands.spawn((
+        Name::from("Campfire"),
+        Campfire,
+        SpriteBundle {
+            texture: handles.resources.clone(),
+            transform: Transform::from_translation(position.extend(0.0)),
+            ..Default::default()
+        },
+        TextureAtlas::from(handles.resources_layout.clone()),
+        SpritesheetAnimation::from_id(animation_id),
+        StateScoped(InRun),
+    ));
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn rest_at_campfires(
+    mut commands: Commands,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Health,
+            &MaxHealth,
+            Option<&mut Resting>,
+        ),
+        With<Player>,
+    >,
+    campfires: Query<(Entity, &Transform), With<Campfire>>,
+    config_handles: Res<ConfigHandles>,
+    config: Res<Assets<CampfireConfig>>,
+    mut hits: EventReader<PlayerHit>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(config) = config.get(config_handles.campfires.id()) else {
+        return error!("Campfire config should be loaded at this point");
+    };
+    let Ok((player, transform, mut health, max_health, resting)) = player.get_single_mut() else {
+        return;
+    };
+    let position = transform.translation.truncate();
+    let hit = hits.read().count() > 0;
+
+    let Some(mut resting) = resting else {
+        if hit {
+            return;
+        }
+        if let Some((campfire, _)) = campfires.iter().find(|(_, campfire)| {
+            campfire.translation.truncate().distance(position) <= config.radius
+        }) {
+            commands.entity(player).insert(Resting {
+                campfire,
+                timer: Timer::from_seconds(config.rest_time, TimerMode::Once),
+            });
+        }
+        return;
+    };
+
+    let nearby = campfires.get(resting.campfire).is_ok_and(|(_, campfire)| {
+        campfire.translation.truncate().distance(position) <= config.radius
+    });
+    if hit || !nearby {
+        commands.entity(player).remove::<Resting>();
+        return;
+    }
+
+    // A level up or chest picked up on the same tick goes first, the rest completes after it
+    if !resting.timer.tick(time.delta()).finished() || matches!(*next_state, NextState::Pending(_))
+    {
+        return;
+    }
+
+    let heal = (max_health.0 as f32 * config.heal).round() as u32;
+    health.0 = (health.0 + heal).min(max_health.0);
+
+    commands.entity(resting.campfire).despawn_recursive();
+    commands.entity(player).remove::<Resting>();
+    next_state.set(GameState::Campfire);
+}
+
+fn expire_buffs(
+    mut player: Query<(&mut TemporaryBuffs, &mut StatModifiers), With<Player>>,
+    time: Res<Time>,
+) {
+    for (mut buffs, mut modifiers) in &mut player {
+        buffs.0.retain_mut(|buff| {
+            if !buff.timer.tick(time.delta()).finished() {
+                return true;
+            }
+
+            if let Some(index) = modifiers
+                .0
+                .iter()
+                .rposition(|modifier| *modifier == buff.modifier)
+            {
+                modifiers.0.remove(index);
+            }
+            false
+        });
+    }
+}
+
+/// Equipped weapons that can still be levelled up, sorted so the buttons keep their order.
+fn upgradable_weapons(
+    player: Entity,
+    weapons: &Query<(&Weapon, &WeaponLevel, &Parent)>,
+) -> Vec<(Weapon, u32)> {
+    let mut upgradable = weapons
+        .iter()
+        .filter(|(_, level, parent)| parent.get() == player && level.0 < MAX_WEAPON_LEVEL)
+        .map(|(weapon, level, _)| (*weapon, level.0))
+        .collect::<Vec<_>>();
+    upgradable.sort_by_key(|(weapon, _)| format!("{weapon:?}"));
+    upgradable
+}
+
+fn price(cost: &[(Resource, u32)]) -> String {
+    cost.iter()
+        .map(|(resource, amount)| format!("{amount} {resource:?}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn spawn_campfire_shop(
+    mut commands: Commands,
+    resources: Res<Resources>,
+    player: Query<Entity, With<Player>>,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+    config_handles: Res<ConfigHandles>,
+    config: Res<Assets<CampfireConfig>>,
+) {
+    let Some(config) = config.get(config_handles.campfires.id()) else {
+        return error!("Campfire config should be loaded at this point");
+    };
+    let upgradable = player
+        .get_single()
+        .map(|player| upgradable_weapons(player, &weapons))
+        .unwrap_or_default();
+
+    let mut offers = config
+        .buffs
+        .iter()
+        .enumerate()
+        .map(|(index, buff)| {
+            (
+                CampfireChoice::Buff(index),
+                format!(
+                    "{} for {}s: {}",
+                    buff.modifier,
+                    buff.duration,
+                    price(&buff.cost)
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+    offers.extend(upgradable.into_iter().map(|(weapon, level)| {
+        (
+            CampfireChoice::WeaponLevel(weapon),
+            format!(
+                "Level up {weapon:?} {level}/{MAX_WEAPON_LEVEL}: {}",
+                price(&config.weapon_level_cost)
+            ),
+        )
+    }));
+    offers.push((CampfireChoice::Leave, "Leave".to_string()));
+
+    commands
+        .spawn((
+            Name::from("Campfire shop"),
+            CampfireShop,
+            StateScoped(GameState::Campfire),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Campfire",
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                resources.to_string(),
+                TextStyle {
+                    font_size: 20.0,
+                    ..Default::default()
+                },
+            ));
+
+            for (choice, label) in offers {
+                parent
+                    .spawn((
+                        CampfireButton(choice),
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(420.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn choose_at_campfire(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    buttons: Query<(&Interaction, &CampfireButton), Changed<Interaction>>,
+    mut source: ResMut<InputSource>,
+    mut resources: ResMut<Resources>,
+    mut player: Query<(Entity, &mut StatModifiers, &mut TemporaryBuffs), With<Player>>,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+    config_handles: Res<ConfigHandles>,
+    config: Res<Assets<CampfireConfig>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(config) = config.get(config_handles.campfires.id()) else {
+        return error!("Campfire config should be loaded at this point");
+    };
+
+    // A replay makes the recorded choice, falling back to the player once it runs out
+    let choice = source.next_campfire_choice().or_else(|| {
+        if input.just_pressed(KeyCode::Escape) {
+            Some(CampfireChoice::Leave)
+        } else {
+            buttons.iter().find_map(|(interaction, button)| {
+                (*interaction == Interaction::Pressed).then_some(button.0)
+            })
+        }
+    });
+    let Some(choice) = choice else {
+        return;
+    };
+    source.record_campfire_choice(choice);
+
+    let Ok((player, mut modifiers, mut buffs)) = player.get_single_mut() else {
+        return next_state.set(GameState::Playing);
+    };
+    match choice {
+        CampfireChoice::Buff(index) => {
+            let Some(buff) = config.buffs.get(index) else {
+                return;
+            };
+            if resources.bypass_change_detection().try_spend(&buff.cost) {
+                resources.set_changed();
+                modifiers.0.push(buff.modifier);
+                buffs.0.push(TemporaryBuff {
+                    modifier: buff.modifier,
+                    timer: Timer::from_seconds(buff.duration, TimerMode::Once),
+                });
+            }
+        }
+        CampfireChoice::WeaponLevel(weapon) => {
+            let upgradable = upgradable_weapons(player, &weapons)
+                .iter()
+                .any(|(upgradable, _)| *upgradable == weapon);
+            if upgradable
+                && resources
+                    .bypass_change_detection()
+                    .try_spend(&config.weapon_level_cost)
+            {
+                resources.set_changed();
+                commands.trigger_targets(UpgradeWeapon(weapon), player);
+            }
+        }
+        CampfireChoice::Leave => next_state.set(GameState::Playing),
+    }
+}
+
+fn refresh_campfire_shop(
+    mut commands: Commands,
+    resources: Res<Resources>,
+    screen: Query<(Entity, Ref<CampfireShop>)>,
+    player: Query<Entity, With<Player>>,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+    config_handles: Res<ConfigHandles>,
+    config: Res<Assets<CampfireConfig>>,
+) {
+    // A shop spawned since the last check already shows the resources as they are
+    if !resources.is_changed() || screen.iter().any(|(_, screen)| screen.is_added()) {
+        return;
+    }
+
+    for (screen, _) in &screen {
+        commands.entity(screen).despawn_recursive();
+    }
+    spawn_campfire_shop(commands, resources, player, weapons, config_handles, config);
+}
+
+fn capture_campfires(
+    mut suspended: ResMut<SuspendedRun>,
+    timer: Res<CampfireTimer>,
+    campfires: Query<&Transform, With<Campfire>>,
+    buffs: Query<&TemporaryBuffs, With<Player>>,
+) {
+    suspended.campfires = CampfireSnapshot {
+        elapsed: timer.elapsed_secs(),
+        positions: campfires
+            .iter()
+            .map(|transform| transform.translation.truncate())
+            .collect(),
+        buffs: buffs
+            .iter()
+            .flat_map(|buffs| &buffs.0)
+            .map(|buff| (buff.modifier, buff.timer.remaining_secs()))
+            .collect(),
+    };
+}
+
+/// The buff modifiers themselves come back with the rest of the player's modifiers.
+fn restore_campfires(
+    mut commands: Commands,
+    resumed: Res<ResumedRun>,
+    mut timer: ResMut<CampfireTimer>,
+    mut buffs: Query<&mut TemporaryBuffs, With<Player>>,
+    handles: Res<GameAssetsHandles>,
+    animations: Res<AnimationLibrary>,
+) {
+    let snapshot = &resumed.campfires;
+    timer.set_elapsed(Duration::from_secs_f32(snapshot.elapsed));
+
+    for position in &snapshot.positions {
+        spawn_campfire(&mut commands, *position, &handles, &animations);
+    }
+
+    for mut buffs in &mut buffs {
+        buffs.0 = snapshot
+            .buffs
+            .iter()
+            .map(|(modifier, remaining)| TemporaryBuff {
+                modifier: *modifier,
+                timer: Timer::from_seconds(*remaining, TimerMode::Once),
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::{CharactersConfig, ConfigHandles, GameAssetsHandles},
+    menu::BUTTON_COLOR,
+    GameState,
+};
+
+const PORTRAIT_SCALE: f32 = 2.0;
+
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedCharacter>()
+            .add_systems(OnEnter(GameState::CharacterSelect), spawn_character_select)
+            .add_systems(
+                Update,
+                choose_character.run_if(in_state(GameState::CharacterSelect)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone, Deref)]
+pub struct SelectedCharacter(pub String);
+
+impl Default for SelectedCharacter {
+    fn default() -> Self {
+        Self("cleric".to_string())
+    }
+}
+
+#[derive(Component)]
+struct CharacterButton(String);
+
+fn spawn_character_select(
+    mut commands: Commands,
+    handles: Res<GameAssetsHandles>,
+    config_handles: Res<ConfigHandles>,
+    characters: Res<Assets<CharactersConfig>>,
+) {
+    let Some(characters) = characters.get(config_handles.characters.id()) else {
+        return error!("Characters config should be loaded at this point");
+    };
+
+    let mut classes = characters.0.iter().collect::<Vec<_>>();
+    classes.sort_by_key(|(name, _)| *name);
+
+    commands
+        .spawn((
+            Name::from("Character select"),
+            StateScoped(GameState::CharacterSelect),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Choose your hero",
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (name, class) in classes {
+                        parent
+                            .spawn((
+                                CharacterButton(name.clone()),
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(200.0),
+                                        padding: UiRect::all(Val::Px(10.0)),
+                                        flex_direction: FlexDirection::Column,
+                                        align_items: AlignItems::Center,
+                                        row_gap: Val::Px(5.0),
+                                        ..Default::default()
+                                    },
+                                    background_color: BUTTON_COLOR.into(),
+                                    ..Default::default()
+                                },
+                            ))
+                            .with_children(|button| {
+                                button.spawn((
+                                    ImageBundle {
+                                        style: Style {
+                                            width: Val::Px(44.0 * PORTRAIT_SCALE),
+                                            height: Val::Px(48.0 * PORTRAIT_SCALE),
+                                            ..Default::default()
+                                        },
+                                        image: UiImage::new(handles.portraits.clone()),
+                                        ..Default::default()
+                                    },
+                                    TextureAtlas {
+                                        layout: handles.portraits_layout.clone(),
+                                        index: class.portrait,
+                                    },
+                                ));
+                                button.spawn(TextBundle::from_section(
+                                    &class.title,
+                                    TextStyle {
+                                        font_size: 24.0,
+                                        ..Default::default()
+                                    },
+                                ));
+                                button.spawn(TextBundle::from_section(
+                                    format!(
+                                        "{:?}, {} health\n{}",
+                                        class.starting_weapon, class.health, class.passive
+                                    ),
+                                    TextStyle {
+                                        font_size: 16.0,
+                                        ..Default::default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn choose_character(
+    buttons: Query<(&Interaction, &CharacterButton), Changed<Interaction>>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        return next_state.set(GameState::MainMenu);
+    }
+
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed {
+            selected.0 = button.0.clone();
+            next_state.set(GameState::Playing);
+        }
+    }
+}
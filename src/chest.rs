@@ -0,0 +1,282 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use bevy_spritesheet_animation::library::AnimationLibrary;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    assets::{ConfigHandles, DropsConfig, GameAssetsHandles, ProgressionConfig},
+    level_up::{apply_upgrade, upgrade_candidates, Upgrade},
+    player::{Player, Weapon, WeaponLevel},
+    resources::{spawn_drops, Loot, Resource, ResourceCollected},
+    run::InRun,
+    stats::{Stat, StatModifiers, Stats},
+    GameState,
+};
+
+/// How long the roulette spins before landing on the reward.
+const SPIN_DURATION: Duration = Duration::from_millis(1500);
+/// How long the reward stays on screen before play resumes.
+const HOLD_DURATION: Duration = Duration::from_millis(1200);
+/// How long every label stays up while spinning.
+const ROULETTE_STEP: Duration = Duration::from_millis(80);
+
+pub struct ChestPlugin;
+
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingChests>()
+            .add_systems(OnEnter(InRun), reset_pending_chests)
+            .add_systems(
+                FixedUpdate,
+                start_chest_reveal
+                    .run_if(in_state(GameState::Playing).and_then(has_pending_chests)),
+            )
+            .add_systems(OnEnter(GameState::Chest), roll_chest_reward)
+            .add_systems(
+                Update,
+                reveal_chest_reward
+                    .run_if(in_state(GameState::Chest).and_then(resource_exists::<ChestReveal>)),
+            )
+            .add_systems(OnExit(GameState::Chest), end_chest_reveal)
+            .observe(on_chest_collected);
+    }
+}
+
+/// Drop tables of the collected chests still waiting for their reveal, in pickup order.
+#[derive(Resource, Debug, Default)]
+struct PendingChests(VecDeque<String>);
+
+#[derive(Debug, Clone)]
+enum ChestReward {
+    Upgrades(Vec<Upgrade>),
+    Drops(Vec<(Loot, u32)>),
+}
+
+impl ChestReward {
+    fn lines(&self) -> Vec<String> {
+        match self {
+            ChestReward::Upgrades(upgrades) => {
+                upgrades.iter().map(|upgrade| upgrade.to_string()).collect()
+            }
+            ChestReward::Drops(drops) => drops
+                .iter()
+                .map(|(loot, amount)| match loot {
+                    Loot::Resource(resource) => format!("{amount} {resource:?}"),
+                    Loot::Chest(_) => format!("{amount} chest"),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+struct ChestReveal {
+    reward: ChestReward,
+    labels: Vec<String>,
+    timer: Timer,
+    revealed: bool,
+}
+
+#[derive(Component)]
+struct RouletteText;
+
+fn reset_pending_chests(mut pending: ResMut<PendingChests>) {
+    pending.0.clear();
+}
+
+fn has_pending_chests(pending: Res<PendingChests>) -> bool {
+    !pending.0.is_empty()
+}
+
+fn on_chest_collected(trigger: Trigger<ResourceCollected>, mut pending: ResMut<PendingChests>) {
+    let event = trigger.event();
+    if let Loot::Chest(table) = &event.loot {
+        for _ in 0..event.amount {
+            pending.0.push_back(table.clone());
+        }
+    }
+}
+
+/// Waits for a level up picked up on the same tick to go first, the chest is revealed once play
+/// resumes.
+fn start_chest_reveal(mut next_state: ResMut<NextState<GameState>>) {
+    if matches!(*next_state, NextState::Pending(_)) {
+        return;
+    }
+
+    next_state.set(GameState::Chest);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn roll_chest_reward(
+    mut commands: Commands,
+    mut pending: ResMut<PendingChests>,
+    player: Query<(Entity, &Stats), With<Player>>,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+    config_handles: Res<ConfigHandles>,
+    progression: Res<Assets<ProgressionConfig>>,
+    drops: Res<Assets<DropsConfig>>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let (Some(progression), Some(drops)) = (
+        progression.get(config_handles.progression.id()),
+        drops.get(config_handles.drops.id()),
+    ) else {
+        return error!("Progression and drops configs should be loaded at this point");
+    };
+    let Some(table) = pending.0.pop_front() else {
+        return next_state.set(GameState::Playing);
+    };
+    let Ok((player, stats)) = player.get_single() else {
+        return next_state.set(GameState::Playing);
+    };
+
+    let equipped = weapons
+        .iter()
+        .filter(|(_, _, parent)| parent.get() == player)
+        .map(|(weapon, level, _)| (*weapon, level.0))
+        .collect::<Vec<_>>();
+    let candidates = upgrade_candidates(&equipped, progression);
+
+    let chest = &progression.chest;
+    let reward = if rng.gen_bool(chest.upgrade_chance.clamp(0.0, 1.0) as f64) {
+        let count = (1..=chest.upgrade_counts.len())
+            .collect::<Vec<_>>()
+            .choose_weighted(&mut *rng, |count| chest.upgrade_counts[count - 1])
+            .copied()
+            .unwrap_or(1);
+        ChestReward::Upgrades(
+            candidates
+                .choose_multiple(&mut *rng, count)
+                .copied()
+                .collect(),
+        )
+    } else {
+        let Some(table) = drops.0.get(&table) else {
+            error!("Unknown drop table {table}");
+            return next_state.set(GameState::Playing);
+        };
+        ChestReward::Drops(table.roll(stats.get(Stat::Luck), &mut *rng))
+    };
+
+    let labels = candidates
+        .iter()
+        .map(|upgrade| upgrade.to_string())
+        .chain(Resource::ALL.iter().map(|resource| format!("{resource:?}")))
+        .collect();
+
+    commands.insert_resource(ChestReveal {
+        reward,
+        labels,
+        timer: Timer::new(SPIN_DURATION, TimerMode::Once),
+        revealed: false,
+    });
+    spawn_reveal_screen(&mut commands);
+}
+
+fn spawn_reveal_screen(commands: &mut Commands) {
+    commands
+        .spawn((
+            Name::from("Chest reveal"),
+            StateScoped(GameState::Chest),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Treasure!",
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+            parent.spawn((
+                RouletteText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        ..Default::default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+            ));
+        });
+}
+
+/// Real time drives the reveal, the virtual clock is paused along with the simulation.
+#[allow(clippy::too_many_arguments)]
+fn reveal_chest_reward(
+    mut commands: Commands,
+    mut reveal: ResMut<ChestReveal>,
+    mut text: Query<&mut Text, With<RouletteText>>,
+    mut player: Query<(Entity, &Transform, &mut StatModifiers), With<Player>>,
+    handles: Res<GameAssetsHandles>,
+    animations: Res<AnimationLibrary>,
+    time: Res<Time<Real>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    reveal.timer.tick(time.delta());
+
+    if reveal.revealed {
+        if reveal.timer.finished() {
+            next_state.set(GameState::Playing);
+        }
+        return;
+    }
+
+    if !reveal.timer.finished() {
+        let step = reveal.timer.elapsed().as_millis() / ROULETTE_STEP.as_millis();
+        if let (Some(label), Ok(mut text)) = (
+            reveal
+                .labels
+                .get(step as usize % reveal.labels.len().max(1)),
+            text.get_single_mut(),
+        ) {
+            text.sections[0].value.clone_from(label);
+        }
+        return;
+    }
+
+    let Ok((player, transform, mut modifiers)) = player.get_single_mut() else {
+        return next_state.set(GameState::Playing);
+    };
+    match &reveal.reward {
+        ChestReward::Upgrades(upgrades) => {
+            for upgrade in upgrades {
+                apply_upgrade(&mut commands, player, &mut modifiers, *upgrade);
+            }
+        }
+        ChestReward::Drops(drops) => spawn_drops(
+            &mut commands,
+            drops.clone(),
+            transform.translation.truncate().extend(0.0),
+            &handles,
+            &animations,
+        ),
+    }
+
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = reveal.reward.lines().join("\n");
+    }
+    reveal.revealed = true;
+    reveal.timer = Timer::new(HOLD_DURATION, TimerMode::Once);
+}
+
+fn end_chest_reveal(mut commands: Commands) {
+    commands.remove_resource::<ChestReveal>();
+}
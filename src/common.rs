@@ -5,3 +5,6 @@ pub struct Speed(pub f32);
 
 #[derive(Debug, Component, Deref, DerefMut)]
 pub struct Health(pub u32);
+
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct MaxHealth(pub u32);
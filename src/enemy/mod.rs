@@ -0,0 +1,493 @@
+mod steering;
+mod wave;
+
+use std::time::Duration;
+
+use avian2d::collision::Collider;
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use bevy_spatial::{kdtree::KDTree2, AutomaticUpdate, SpatialAccess, SpatialStructure};
+use bevy_spritesheet_animation::{events::AnimationEvent, prelude::SpritesheetAnimation};
+
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+
+use crate::{
+    animation::{AnimationController, AnimationSets, AnimationState},
+    assets::{ConfigHandles, EnemiesConfig, EnemyArchetype, GameAssetsHandles},
+    common::{Health, Speed},
+    input::PlayerInput,
+    interpolation::Interpolated,
+    player::Player,
+    run::InRun,
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    GameState,
+};
+
+use wave::WavePlugin;
+
+pub use wave::{CurrentWave, Formation, SpawnEnemies, WaveSnapshot};
+
+const ELITE_SCALE: f32 = 1.5;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyKilled>()
+            .add_plugins((
+                WavePlugin,
+                // Rebuilt every tick ahead of the simulation, steering reads it and has to see the
+                // same neighbours when a run is played back
+                AutomaticUpdate::<NearestNeighbour>::new()
+                    .with_spatial_ds(SpatialStructure::KDTree2)
+                    .with_schedule(FixedPreUpdate)
+                    .with_frequency(Duration::ZERO),
+            ))
+            .add_systems(
+                FixedUpdate,
+                (
+                    steering::move_towards_player,
+                    spawn_enemy,
+                    on_death_animation_end,
+                    add_colliders_to_close_enemies,
+                    kill_all_on_screen,
+                )
+                    .distributive_run_if(
+                        in_state(GameState::Playing).and_then(any_with_component::<Player>),
+                    ),
+            )
+            .add_systems(
+                Update,
+                (enemy_direction_change, on_dying).distributive_run_if(
+                    in_state(GameState::Playing).and_then(any_with_component::<Player>),
+                ),
+            )
+            .add_systems(Update, capture_enemies.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_enemies.in_set(SuspendSystems::Restore));
+
+        #[cfg(feature = "dev")]
+        app.add_systems(Update, reapply_archetypes);
+    }
+}
+
+#[derive(Bundle, Debug)]
+pub struct EnemyBundle {
+    name: Name,
+    enemy: Enemy,
+    speed: Speed,
+    health: Health,
+    contact_damage: ContactDamage,
+    stop_distance: StopDistance,
+    sprite_bundle: SpriteBundle,
+    texture_atlas: TextureAtlas,
+    sprite_sheet_animation: SpritesheetAnimation,
+    animation_controller: AnimationController,
+    collider_size: ColliderSize,
+}
+
+impl EnemyBundle {
+    fn new(
+        name: &str,
+        archetype: &EnemyArchetype,
+        spawn_point: Vec3,
+        monsters_handles: &GameAssetsHandles,
+        animation_sets: &AnimationSets,
+    ) -> Option<Self> {
+        let texture_atlas_layout: &Handle<TextureAtlasLayout> =
+            monsters_handles.get_field(&archetype.layout)?;
+        let animation_controller =
+            AnimationController::new(animation_sets.enemies.get(name)?.clone());
+        Some(Self {
+            name: Name::from(name),
+            speed: Speed(archetype.speed),
+            health: Health(archetype.health),
+            contact_damage: ContactDamage(archetype.contact_damage),
+            stop_distance: StopDistance(archetype.stop_distance),
+            enemy: Enemy,
+            sprite_bundle: SpriteBundle {
+                texture: monsters_handles
+                    .get_monster_sheet_handle(&archetype.sheet)?
+                    .clone(),
+                transform: Transform::from_translation(spawn_point),
+                ..Default::default()
+            },
+            texture_atlas: TextureAtlas::from(texture_atlas_layout.clone()),
+            sprite_sheet_animation: SpritesheetAnimation::from_id(
+                animation_controller.initial_animation()?,
+            ),
+            animation_controller,
+            collider_size: ColliderSize(Vec2::new(archetype.collider.0, archetype.collider.1)),
+        })
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct EnemySnapshot {
+    pub archetype: String,
+    pub position: Vec2,
+    pub health: u32,
+    pub elite: bool,
+}
+
+#[allow(clippy::type_complexity)]
+fn add_colliders_to_close_enemies(
+    mut commands: Commands,
+    close_enemies: Res<KDTree2<NearestNeighbour>>,
+    enemies_without_collider: Query<
+        (Entity, &ColliderSize),
+        (With<Enemy>, Without<Collider>, Without<Dying>),
+    >,
+    player: Query<&Transform, With<Player>>,
+) {
+    let player = player.single();
+    let close_enemies = close_enemies.within_distance(player.translation.truncate(), 200.0);
+    for (_, enemy) in &close_enemies {
+        if let Some(enemy) = enemy {
+            if let Ok((enemy, size)) = enemies_without_collider.get(*enemy) {
+                commands
+                    .entity(enemy)
+                    .insert(Collider::rectangle(size.x, size.y));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Enemy;
+
+#[derive(Debug, Component)]
+pub struct ContactDamage(pub u32);
+
+#[derive(Debug, Component)]
+pub struct StopDistance(pub f32);
+
+#[derive(Debug, Component)]
+pub struct Elite;
+
+#[derive(Component, Deref, DerefMut)]
+struct DotTimer(Timer);
+
+#[derive(Debug, Component, Deref)]
+struct ColliderSize(Vec2);
+
+#[derive(Debug)]
+enum SpawnDirection {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl SpawnDirection {
+    fn calculate_x(&self, window: &Window, player: &Vec3) -> Option<f32> {
+        match self {
+            SpawnDirection::North => None,
+            SpawnDirection::South => None,
+            SpawnDirection::West => Some((player.x - window.width() / 2.0) - 30.0),
+            SpawnDirection::East => Some((player.x + window.width() / 2.0) + 30.0),
+        }
+    }
+
+    fn calculate_y(&self, window: &Window, player: &Vec3) -> Option<f32> {
+        match self {
+            SpawnDirection::South => Some((player.y - window.height() / 2.0) - 30.0),
+            SpawnDirection::North => Some((player.y + window.height() / 2.0) + 30.0),
+            SpawnDirection::West => None,
+            SpawnDirection::East => None,
+        }
+    }
+}
+
+impl Distribution<SpawnDirection> for Standard {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> SpawnDirection {
+        match rng.gen_range(0..=3) {
+            0 => SpawnDirection::East,
+            1 => SpawnDirection::North,
+            2 => SpawnDirection::South,
+            _ => SpawnDirection::West,
+        }
+    }
+}
+
+#[derive(Event)]
+enum SpriteDirection {
+    Left,
+    Right,
+}
+
+#[allow(clippy::type_complexity)]
+fn enemy_direction_change(
+    mut commands: Commands,
+    player: Query<&GlobalTransform, With<Player>>,
+    enemies: Query<(&GlobalTransform, Entity), (With<Enemy>, Without<Dying>)>,
+) {
+    let player = player.single();
+    for (enemy, entity) in &enemies {
+        if enemy.translation().x < player.translation().x {
+            commands.trigger_targets(SpriteDirection::Right, entity)
+        } else {
+            commands.trigger_targets(SpriteDirection::Left, entity)
+        }
+    }
+}
+
+fn on_direction_changed(
+    trigger: Trigger<SpriteDirection>,
+    mut sprites: Query<&mut Sprite, With<Enemy>>,
+) {
+    let mut sprite = sprites.get_mut(trigger.entity()).unwrap();
+    match trigger.event() {
+        SpriteDirection::Left => sprite.flip_x = true,
+        SpriteDirection::Right => sprite.flip_x = false,
+    }
+}
+
+fn edge_spawn_point<R: Rng + ?Sized>(window: &Window, player: &Vec3, rng: &mut R) -> Vec2 {
+    let spawn_direction: SpawnDirection = rng.gen();
+    let x = spawn_direction.calculate_x(window, player);
+    let y = spawn_direction.calculate_y(window, player);
+
+    match (x, y) {
+        (Some(x), _) => Vec2::new(
+            x,
+            rng.gen_range((player.y - window.height() / 2.0)..player.y + window.height() / 2.0),
+        ),
+        (_, Some(y)) => Vec2::new(
+            rng.gen_range((player.x - window.width() / 2.0)..player.x + window.width() / 2.0),
+            y,
+        ),
+        _ => unreachable!("This should never happen"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_enemy(
+    window: Query<&Window, With<PrimaryWindow>>,
+    player: Query<&Transform, With<Player>>,
+    mut requests: EventReader<SpawnEnemies>,
+    mut commands: Commands,
+    monsters_handles: Res<GameAssetsHandles>,
+    animation_sets: Res<AnimationSets>,
+    config_handles: Res<ConfigHandles>,
+    enemies_config: Res<Assets<EnemiesConfig>>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    if requests.is_empty() {
+        return;
+    }
+
+    let Some(enemies_config) = enemies_config.get(config_handles.enemies.id()) else {
+        return error!("Enemies config should be loaded at this point");
+    };
+
+    let player = player.single().translation;
+    let window = window.single();
+
+    for request in requests.read() {
+        let Some(archetype) = enemies_config.0.get(&request.archetype) else {
+            error!("Unknown enemy archetype {}", request.archetype);
+            continue;
+        };
+
+        let spawn_points = match request.formation {
+            Formation::Cluster => {
+                let center = edge_spawn_point(window, &player, &mut *rng);
+                let spread = 20.0 * (request.count as f32).sqrt();
+                (0..request.count)
+                    .map(|_| {
+                        center
+                            + Vec2::new(
+                                rng.gen_range(-spread..=spread),
+                                rng.gen_range(-spread..=spread),
+                            )
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Formation::Ring { radius } => (0..request.count)
+                .map(|index| {
+                    let angle = std::f32::consts::TAU * index as f32 / request.count as f32;
+                    player.truncate() + Vec2::from_angle(angle) * radius
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        for spawn_point in spawn_points {
+            let Some(mut bundle) = EnemyBundle::new(
+                &request.archetype,
+                archetype,
+                spawn_point.extend(0.0),
+                &monsters_handles,
+                &animation_sets,
+            ) else {
+                error!("Failed to create enemy bundle for {}", request.archetype);
+                continue;
+            };
+
+            bundle.health.0 = (bundle.health.0 as f32 * request.health_multiplier) as u32;
+            spawn_enemy_bundle(&mut commands, bundle, request.elite);
+        }
+    }
+}
+
+fn spawn_enemy_bundle(commands: &mut Commands, mut bundle: EnemyBundle, elite: bool) {
+    if elite {
+        bundle.sprite_bundle.transform.scale = Vec3::splat(ELITE_SCALE);
+        bundle.collider_size.0 *= ELITE_SCALE;
+    }
+
+    let mut enemy = commands.spawn((
+        bundle,
+        DotTimer(Timer::from_seconds(2.0, TimerMode::Repeating)),
+        NearestNeighbour,
+        Interpolated::default(),
+        StateScoped(InRun),
+    ));
+    enemy.observe(on_direction_changed);
+
+    if elite {
+        enemy.insert(Elite);
+    }
+}
+
+fn capture_enemies(
+    mut suspended: ResMut<SuspendedRun>,
+    enemies: Query<(&Name, &Transform, &Health, Has<Elite>), (With<Enemy>, Without<Dying>)>,
+) {
+    suspended.enemies = enemies
+        .iter()
+        .map(|(name, transform, health, elite)| EnemySnapshot {
+            archetype: name.to_string(),
+            position: transform.translation.truncate(),
+            health: health.0,
+            elite,
+        })
+        .collect();
+}
+
+fn restore_enemies(
+    mut commands: Commands,
+    resumed: Res<ResumedRun>,
+    monsters_handles: Res<GameAssetsHandles>,
+    animation_sets: Res<AnimationSets>,
+    config_handles: Res<ConfigHandles>,
+    enemies_config: Res<Assets<EnemiesConfig>>,
+) {
+    let Some(enemies_config) = enemies_config.get(config_handles.enemies.id()) else {
+        return error!("Enemies config should be loaded at this point");
+    };
+
+    for enemy in &resumed.enemies {
+        let Some(mut bundle) = enemies_config
+            .0
+            .get(&enemy.archetype)
+            .and_then(|archetype| {
+                EnemyBundle::new(
+                    &enemy.archetype,
+                    archetype,
+                    enemy.position.extend(0.0),
+                    &monsters_handles,
+                    &animation_sets,
+                )
+            })
+        else {
+            error!("Failed to restore enemy {}", enemy.archetype);
+            continue;
+        };
+
+        bundle.health.0 = enemy.health;
+        spawn_enemy_bundle(&mut commands, bundle, enemy.elite);
+    }
+}
+
+#[derive(Component)]
+pub struct Dying;
+
+#[derive(Event, Debug)]
+pub struct EnemyKilled {
+    pub archetype: String,
+    pub position: Vec3,
+    pub elite: bool,
+}
+
+#[derive(Component, Default)]
+pub struct NearestNeighbour;
+
+#[allow(clippy::type_complexity)]
+fn on_dying(
+    mut query: Query<
+        (&mut AnimationController, &mut SpritesheetAnimation),
+        (Added<Dying>, With<Enemy>),
+    >,
+) {
+    for (mut controller, mut animation) in &mut query {
+        controller.transition(AnimationState::Death, &mut animation);
+    }
+}
+
+/// Live enemies pick up speed and damage changes to their archetype, health is left alone as
+/// there's no telling how much of it they already lost.
+#[cfg(feature = "dev")]
+fn reapply_archetypes(
+    mut events: EventReader<AssetEvent<EnemiesConfig>>,
+    config_handles: Res<ConfigHandles>,
+    enemies_config: Res<Assets<EnemiesConfig>>,
+    mut enemies: Query<(&Name, &mut Speed, &mut ContactDamage, &mut StopDistance), With<Enemy>>,
+) {
+    if events
+        .read()
+        .filter(|event| event.is_modified(config_handles.enemies.id()))
+        .count()
+        == 0
+    {
+        return;
+    }
+
+    let Some(enemies_config) = enemies_config.get(config_handles.enemies.id()) else {
+        return;
+    };
+
+    for (name, mut speed, mut contact_damage, mut stop_distance) in &mut enemies {
+        if let Some(archetype) = enemies_config.0.get(name.as_str()) {
+            speed.0 = archetype.speed;
+            contact_damage.0 = archetype.contact_damage;
+            stop_distance.0 = archetype.stop_distance;
+        }
+    }
+}
+
+fn kill_all_on_screen(
+    mut commands: Commands,
+    enemies: Query<Entity, (With<Enemy>, Without<Dying>)>,
+    input: Res<PlayerInput>,
+) {
+    if input.kill_all {
+        let enemies = enemies
+            .into_iter()
+            .map(|entity| (entity, Dying))
+            .collect::<Vec<_>>();
+        commands.insert_or_spawn_batch(enemies);
+    }
+}
+
+fn on_death_animation_end(
+    mut commands: Commands,
+    mut events: EventReader<AnimationEvent>,
+    mut killed: EventWriter<EnemyKilled>,
+    dying_enemies: Query<(&Name, &Transform, Has<Elite>), (With<Enemy>, With<Dying>)>,
+) {
+    for animation_event in events.read() {
+        if let AnimationEvent::AnimationEnd { entity, .. } = animation_event {
+            if let Ok((name, transform, elite)) = dying_enemies.get(*entity) {
+                killed.send(EnemyKilled {
+                    archetype: name.to_string(),
+                    position: transform.translation,
+                    elite,
+                });
+                commands.entity(*entity).despawn_recursive();
+            }
+        }
+    }
+}
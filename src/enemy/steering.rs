@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy_spatial::{kdtree::KDTree2, SpatialAccess};
+
+use crate::{
+    common::Speed,
+    player::Player,
+    terrain::{LoadedChunks, BODY_RADIUS},
+};
+
+use super::{Dying, Enemy, NearestNeighbour, StopDistance};
+
+/// Enemies closer than this to each other push apart.
+const SEPARATION_RADIUS: f32 = 24.0;
+const SEPARATION_WEIGHT: f32 = 1.5;
+/// Enemies start steering around an obstacle this far before bumping into it.
+const AVOIDANCE_MARGIN: f32 = 24.0;
+const AVOIDANCE_WEIGHT: f32 = 2.0;
+
+/// Every enemy seeks the player until its stop distance, while keeping away from its neighbours
+/// and veering around obstacles in its way.
+#[allow(clippy::type_complexity)]
+pub(super) fn move_towards_player(
+    player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemies: Query<
+        (Entity, &mut Transform, &Speed, &StopDistance),
+        (With<Enemy>, Without<Dying>),
+    >,
+    neighbours: Res<KDTree2<NearestNeighbour>>,
+    chunks: Res<LoadedChunks>,
+    time: Res<Time>,
+) {
+    let player = player.single().translation.truncate();
+
+    for (entity, mut transform, speed, stop_distance) in &mut enemies {
+        let position = transform.translation.truncate();
+
+        let to_player = player - position;
+        let seek = if to_player.length() > stop_distance.0 {
+            to_player.normalize_or_zero()
+        } else {
+            Vec2::ZERO
+        };
+
+        let separation = neighbours
+            .within_distance(position, SEPARATION_RADIUS)
+            .into_iter()
+            .filter(|(_, neighbour)| *neighbour != Some(entity))
+            .fold(Vec2::ZERO, |separation, (neighbour, _)| {
+                let away = position - neighbour;
+                let distance = away.length();
+                if distance <= f32::EPSILON {
+                    return separation;
+                }
+                separation + away / distance * (1.0 - distance / SEPARATION_RADIUS)
+            });
+
+        let avoidance = chunks
+            .obstacles_near(position, BODY_RADIUS + AVOIDANCE_MARGIN)
+            .fold(Vec2::ZERO, |avoidance, obstacle| {
+                let to_obstacle = obstacle.center - position;
+                let reach = obstacle.radius + BODY_RADIUS + AVOIDANCE_MARGIN;
+                let distance = to_obstacle.length();
+                if distance >= reach || seek.dot(to_obstacle) <= 0.0 {
+                    return avoidance;
+                }
+
+                // Sideways, around whichever side of the obstacle the enemy is already on
+                let side = seek.perp();
+                let side = if side.dot(to_obstacle) > 0.0 {
+                    -side
+                } else {
+                    side
+                };
+                avoidance + side * (1.0 - distance / reach)
+            });
+
+        let steering = (seek + separation * SEPARATION_WEIGHT + avoidance * AVOIDANCE_WEIGHT)
+            .clamp_length_max(1.0);
+        if steering != Vec2::ZERO {
+            transform.translation += (steering * speed.0 * time.delta_seconds()).extend(0.0);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    assets::{ConfigHandles, EnemiesConfig, ScriptedEventKind, WaveDefinition, WavesConfig},
+    player::Player,
+    run::InRun,
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    GameState,
+};
+
+use super::spawn_enemy;
+
+/// Shortest time between two spawned groups, a zero interval would spawn endlessly in one tick.
+const MIN_SPAWN_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnEnemies>()
+            .init_resource::<WaveDirector>()
+            .init_resource::<CurrentWave>()
+            .add_systems(OnEnter(InRun), reset_waves)
+            .add_systems(Update, capture_waves.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_waves.in_set(SuspendSystems::Restore))
+            .add_systems(
+                FixedUpdate,
+                direct_waves
+                    .before(spawn_enemy)
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Default, Deref)]
+pub struct CurrentWave(pub u32);
+
+#[derive(Resource, Debug, Default)]
+struct WaveDirector {
+    elapsed: Duration,
+    spawn_timer: Timer,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct WaveSnapshot {
+    pub current: u32,
+    pub elapsed: Duration,
+    pub spawn_interval: Duration,
+    pub spawn_elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub enum Formation {
+    /// Enemies appear bunched together just outside one of the screen edges.
+    Cluster,
+    /// Enemies appear evenly spaced on a circle around the player.
+    Ring { radius: f32 },
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SpawnEnemies {
+    pub archetype: String,
+    pub count: u32,
+    pub formation: Formation,
+    pub elite: bool,
+    /// Scales the archetype's health, `1.0` leaves it as configured.
+    pub health_multiplier: f32,
+}
+
+impl From<&ScriptedEventKind> for SpawnEnemies {
+    fn from(kind: &ScriptedEventKind) -> Self {
+        match kind {
+            ScriptedEventKind::Swarm { archetype, count } => Self {
+                archetype: archetype.clone(),
+                count: *count,
+                formation: Formation::Cluster,
+                elite: false,
+                health_multiplier: 1.0,
+            },
+            ScriptedEventKind::Ring {
+                archetype,
+                count,
+                radius,
+            } => Self {
+                archetype: archetype.clone(),
+                count: *count,
+                formation: Formation::Ring { radius: *radius },
+                elite: false,
+                health_multiplier: 1.0,
+            },
+            ScriptedEventKind::Elite {
+                archetype,
+                count,
+                health_multiplier,
+            } => Self {
+                archetype: archetype.clone(),
+                count: *count,
+                formation: Formation::Cluster,
+                elite: true,
+                health_multiplier: *health_multiplier,
+            },
+        }
+    }
+}
+
+fn reset_waves(mut director: ResMut<WaveDirector>, mut current_wave: ResMut<CurrentWave>) {
+    *director = WaveDirector::default();
+    *current_wave = CurrentWave::default();
+}
+
+fn capture_waves(
+    mut suspended: ResMut<SuspendedRun>,
+    director: Res<WaveDirector>,
+    current_wave: Res<CurrentWave>,
+) {
+    suspended.wave = WaveSnapshot {
+        current: current_wave.0,
+        elapsed: director.elapsed,
+        spawn_interval: director.spawn_timer.duration(),
+        spawn_elapsed: director.spawn_timer.elapsed(),
+    };
+}
+
+fn restore_waves(
+    resumed: Res<ResumedRun>,
+    mut director: ResMut<WaveDirector>,
+    mut current_wave: ResMut<CurrentWave>,
+) {
+    let wave = &resumed.wave;
+    let mut spawn_timer = spawn_timer(wave.spawn_interval);
+    spawn_timer.set_elapsed(wave.spawn_elapsed);
+
+    *director = WaveDirector {
+        elapsed: wave.elapsed,
+        spawn_timer,
+    };
+    current_wave.0 = wave.current;
+}
+
+fn choose_archetype<R: Rng + ?Sized>(
+    wave: &WaveDefinition,
+    enemies_config: &EnemiesConfig,
+    rng: &mut R,
+) -> Option<String> {
+    if wave.archetypes.is_empty() {
+        return enemies_config.choose(rng).map(|(name, _)| name.clone());
+    }
+
+    let mut archetypes = wave.archetypes.iter().collect::<Vec<_>>();
+    archetypes.sort_by_key(|(name, _)| *name);
+    archetypes
+        .choose_weighted(rng, |(_, weight)| **weight)
+        .ok()
+        .map(|(name, _)| (*name).clone())
+}
+
+fn spawn_timer(interval: Duration) -> Timer {
+    Timer::new(interval.max(MIN_SPAWN_INTERVAL), TimerMode::Repeating)
+}
+
+fn direct_waves(
+    mut director: ResMut<WaveDirector>,
+    mut current_wave: ResMut<CurrentWave>,
+    mut writer: EventWriter<SpawnEnemies>,
+    time: Res<Time>,
+    config_handles: Res<ConfigHandles>,
+    waves_config: Res<Assets<WavesConfig>>,
+    enemies_config: Res<Assets<EnemiesConfig>>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    let (Some(waves_config), Some(enemies_config)) = (
+        waves_config.get(config_handles.waves.id()),
+        enemies_config.get(config_handles.enemies.id()),
+    ) else {
+        return error!("Waves and enemies configs should be loaded at this point");
+    };
+
+    let previous = director.elapsed.as_secs_f32();
+    director.elapsed += time.delta();
+    let elapsed = director.elapsed.as_secs_f32();
+
+    // Scripted events may be timed before the first wave starts
+    for event in &waves_config.events {
+        if (previous..elapsed).contains(&event.at) {
+            writer.send(SpawnEnemies::from(&event.kind));
+        }
+    }
+
+    let Some(wave_index) = waves_config.wave_at(elapsed) else {
+        return;
+    };
+    let wave = &waves_config.waves[wave_index];
+
+    if current_wave.0 != wave_index as u32 + 1 {
+        current_wave.0 = wave_index as u32 + 1;
+        director.spawn_timer = spawn_timer(Duration::from_secs_f32(wave.spawn_interval.max(0.0)));
+        info!("Wave {} started", current_wave.0);
+    }
+
+    director.spawn_timer.tick(time.delta());
+    for _ in 0..director.spawn_timer.times_finished_this_tick() {
+        let Some(archetype) = choose_archetype(wave, enemies_config, &mut *rng) else {
+            continue;
+        };
+
+        let (min, max) = wave.group_size;
+        writer.send(SpawnEnemies {
+            archetype,
+            count: rng.gen_range(min..=max.max(min)),
+            formation: Formation::Cluster,
+            elite: false,
+            health_multiplier: 1.0,
+        });
+    }
+}
@@ -0,0 +1,178 @@
+use avian2d::prelude::{Collider, CollidingEntities};
+use bevy::prelude::*;
+
+use crate::{
+    assets::{ConfigHandles, ProgressionConfig},
+    enemy::EnemyKilled,
+    player::Player,
+    resources::Pickup,
+    run::InRun,
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    GameState,
+};
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelUp>()
+            .add_systems(
+                FixedUpdate,
+                (drop_experience_gems, collect_experience_gems)
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            )
+            .add_systems(Update, capture_gems.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_gems.in_set(SuspendSystems::Restore));
+    }
+}
+
+#[derive(Component, Debug, Default, Deref)]
+pub struct Experience(pub u32);
+
+#[derive(Component, Debug, Deref)]
+pub struct Level(pub u32);
+
+impl Default for Level {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct LevelUp {
+    pub level: u32,
+}
+
+#[derive(Component, Debug)]
+pub struct ExperienceGem {
+    value: u32,
+    elite: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct GemSnapshot {
+    pub position: Vec2,
+    pub value: u32,
+    pub elite: bool,
+}
+
+#[derive(Bundle)]
+struct ExperienceGemBundle {
+    name: Name,
+    gem: ExperienceGem,
+    pickup: Pickup,
+    sprite_bundle: SpriteBundle,
+    collider: Collider,
+    state_scoped: StateScoped<InRun>,
+}
+
+impl ExperienceGemBundle {
+    fn new(value: u32, elite: bool, translation: Vec3) -> Self {
+        let (color, size) = if elite {
+            (Color::srgb(0.8, 0.3, 1.0), 12.0)
+        } else {
+            (Color::srgb(0.3, 0.8, 1.0), 8.0)
+        };
+
+        Self {
+            name: Name::from("Experience gem"),
+            gem: ExperienceGem { value, elite },
+            pickup: Pickup,
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(translation)
+                    .with_rotation(Quat::from_rotation_z(45.0_f32.to_radians())),
+                ..Default::default()
+            },
+            collider: Collider::rectangle(size, size),
+            state_scoped: StateScoped(InRun),
+        }
+    }
+}
+
+fn drop_experience_gems(
+    mut commands: Commands,
+    mut killed: EventReader<EnemyKilled>,
+    config_handles: Res<ConfigHandles>,
+    progression: Res<Assets<ProgressionConfig>>,
+) {
+    let Some(progression) = progression.get(config_handles.progression.id()) else {
+        return error!("Progression config should be loaded at this point");
+    };
+
+    for killed_enemy in killed.read() {
+        let value = if killed_enemy.elite {
+            progression.elite_gem_value
+        } else {
+            progression.gem_value
+        };
+
+        // Offset a bit so the gem doesn't hide below the resource dropped by the same enemy
+        commands.spawn(ExperienceGemBundle::new(
+            value,
+            killed_enemy.elite,
+            killed_enemy.position + Vec3::new(10.0, -10.0, 0.0),
+        ));
+    }
+}
+
+fn capture_gems(mut suspended: ResMut<SuspendedRun>, gems: Query<(&ExperienceGem, &Transform)>) {
+    suspended.gems = gems
+        .iter()
+        .map(|(gem, transform)| GemSnapshot {
+            position: transform.translation.truncate(),
+            value: gem.value,
+            elite: gem.elite,
+        })
+        .collect();
+}
+
+fn restore_gems(mut commands: Commands, resumed: Res<ResumedRun>) {
+    for gem in &resumed.gems {
+        commands.spawn(ExperienceGemBundle::new(
+            gem.value,
+            gem.elite,
+            gem.position.extend(0.0),
+        ));
+    }
+}
+
+fn collect_experience_gems(
+    mut commands: Commands,
+    mut player: Query<(&CollidingEntities, &mut Experience, &mut Level), With<Player>>,
+    gems: Query<&ExperienceGem>,
+    config_handles: Res<ConfigHandles>,
+    progression: Res<Assets<ProgressionConfig>>,
+    mut level_ups: EventWriter<LevelUp>,
+) {
+    let Some(progression) = progression.get(config_handles.progression.id()) else {
+        return error!("Progression config should be loaded at this point");
+    };
+
+    let Ok((CollidingEntities(collisions), mut experience, mut level)) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    for entity in collisions {
+        if let Ok(gem) = gems.get(*entity) {
+            experience.0 += gem.value;
+            commands.entity(*entity).despawn_recursive();
+        }
+    }
+
+    loop {
+        let threshold = progression.experience_curve.threshold(level.0).max(1);
+        if experience.0 < threshold {
+            break;
+        }
+
+        experience.0 -= threshold;
+        level.0 += 1;
+        level_ups.send(LevelUp { level: level.0 });
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::{ConfigHandles, GameAssetsHandles, ProgressionConfig},
+    common::{Health, MaxHealth},
+    experience::{Experience, Level},
+    player::Player,
+    resources::{Loot, Resource, ResourceCollected, Resources},
+    run::{InRun, RunStats},
+    suspend::{ResumedRun, SuspendSystems},
+    GameState,
+};
+
+const BAR_WIDTH: f32 = 200.0;
+const BAR_HEIGHT: f32 = 12.0;
+const ICON_SIZE: f32 = 24.0;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InRun), spawn_hud)
+            .add_systems(
+                Update,
+                (update_health_bar, update_experience_bar, update_run_stats)
+                    .run_if(in_state(InRun)),
+            )
+            .add_systems(OnExit(GameState::Campfire), sync_resource_counters)
+            .add_systems(
+                FixedFirst,
+                sync_resource_counters
+                    .after(SuspendSystems::Restore)
+                    .run_if(resource_exists::<ResumedRun>),
+            )
+            .observe(update_resource_counter);
+    }
+}
+
+#[derive(Component)]
+struct HealthBar;
+
+#[derive(Component)]
+struct ExperienceBar;
+
+#[derive(Component)]
+struct RunTimeText;
+
+#[derive(Component)]
+struct KillsText;
+
+#[derive(Component)]
+struct LevelText;
+
+#[derive(Component)]
+struct ResourceCounter {
+    resource: Resource,
+    count: u32,
+}
+
+fn spawn_hud(mut commands: Commands, handles: Res<GameAssetsHandles>) {
+    commands
+        .spawn((
+            Name::from("HUD"),
+            StateScoped(InRun),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(5.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    spawn_bar(parent, HealthBar, Color::srgb(0.8, 0.1, 0.1));
+                    spawn_bar(parent, ExperienceBar, Color::srgb(0.3, 0.8, 1.0));
+                    spawn_text(parent, LevelText, "Level 1");
+                    spawn_text(parent, RunTimeText, "0:00");
+                    spawn_text(parent, KillsText, "Kills 0");
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(10.0),
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for resource in Resource::ALL {
+                        parent.spawn((
+                            ImageBundle {
+                                style: Style {
+                                    width: Val::Px(ICON_SIZE),
+                                    height: Val::Px(ICON_SIZE),
+                                    ..Default::default()
+                                },
+                                image: UiImage::new(handles.resources.clone()),
+                                ..Default::default()
+                            },
+                            TextureAtlas {
+                                layout: handles.resources_layout.clone(),
+                                index: resource.icon_index(),
+                            },
+                        ));
+                        spawn_text(parent, ResourceCounter { resource, count: 0 }, "0");
+                    }
+                });
+        });
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, marker: impl Component, color: Color) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(BAR_WIDTH),
+                height: Val::Px(BAR_HEIGHT),
+                ..Default::default()
+            },
+            background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                marker,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: color.into(),
+                    ..Default::default()
+                },
+            ));
+        });
+}
+
+fn spawn_text(parent: &mut ChildBuilder, marker: impl Component, text: &str) {
+    parent.spawn((
+        marker,
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font_size: 20.0,
+                ..Default::default()
+            },
+        ),
+    ));
+}
+
+fn update_resource_counter(
+    trigger: Trigger<ResourceCollected>,
+    mut counters: Query<(&mut ResourceCounter, &mut Text)>,
+) {
+    let event = trigger.event();
+    let Loot::Resource(resource) = event.loot else {
+        return;
+    };
+    let Some((mut counter, mut text)) = counters
+        .iter_mut()
+        .find(|(counter, _)| counter.resource == resource)
+    else {
+        return;
+    };
+
+    counter.count += event.amount;
+    text.sections[0].value = counter.count.to_string();
+}
+
+fn sync_resource_counters(
+    resources: Res<Resources>,
+    mut counters: Query<(&mut ResourceCounter, &mut Text)>,
+) {
+    for (mut counter, mut text) in &mut counters {
+        counter.count = resources.get(counter.resource);
+        text.sections[0].value = counter.count.to_string();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_health_bar(
+    player: Query<(&Health, &MaxHealth), (With<Player>, Or<(Changed<Health>, Changed<MaxHealth>)>)>,
+    mut bar: Query<&mut Style, With<HealthBar>>,
+) {
+    let (Ok((health, max_health)), Ok(mut bar)) = (player.get_single(), bar.get_single_mut())
+    else {
+        return;
+    };
+
+    bar.width = Val::Percent(health.0 as f32 / max_health.0.max(1) as f32 * 100.0);
+}
+
+#[allow(clippy::type_complexity)]
+fn update_experience_bar(
+    player: Query<(&Experience, &Level), (With<Player>, Or<(Changed<Experience>, Changed<Level>)>)>,
+    mut bar: Query<&mut Style, With<ExperienceBar>>,
+    mut level_text: Query<&mut Text, With<LevelText>>,
+    config_handles: Res<ConfigHandles>,
+    progression: Res<Assets<ProgressionConfig>>,
+) {
+    let Ok((experience, level)) = player.get_single() else {
+        return;
+    };
+    let Some(progression) = progression.get(config_handles.progression.id()) else {
+        return error!("Progression config should be loaded at this point");
+    };
+
+    let threshold = progression.experience_curve.threshold(level.0).max(1);
+    if let Ok(mut bar) = bar.get_single_mut() {
+        bar.width = Val::Percent(experience.0 as f32 / threshold as f32 * 100.0);
+    }
+    if let Ok(mut text) = level_text.get_single_mut() {
+        text.sections[0].value = format!("Level {}", level.0);
+    }
+}
+
+fn update_run_stats(
+    stats: Res<RunStats>,
+    mut run_time: Query<&mut Text, (With<RunTimeText>, Without<KillsText>)>,
+    mut kills: Query<&mut Text, (With<KillsText>, Without<RunTimeText>)>,
+) {
+    if !stats.is_changed() {
+        return;
+    }
+
+    let elapsed = stats.elapsed.as_secs();
+    if let Ok(mut text) = run_time.get_single_mut() {
+        text.sections[0].value = format!("{}:{:02}", elapsed / 60, elapsed % 60);
+    }
+    if let Ok(mut text) = kills.get_single_mut() {
+        text.sections[0].value = format!("Kills {}", stats.kills);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{
+    character::SelectedCharacter,
+    meta::Bank,
+    player::Weapon,
+    run::{InRun, RunSeed},
+    GameState,
+};
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerInput>()
+            .add_systems(Update, start_replay.run_if(in_state(GameState::MainMenu)))
+            .add_systems(OnEnter(InRun), start_recording)
+            .add_systems(
+                FixedPreUpdate,
+                sample_player_input.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(InRun), save_recording);
+    }
+}
+
+/// Everything the player controls during a single simulation tick. Gameplay systems read this
+/// instead of the keyboard, so a recorded run can be fed back in.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct PlayerInput {
+    /// Raw movement direction, every axis is either `-1`, `0` or `1`.
+    pub movement: Vec2,
+    /// Debug shortcut killing every enemy, only set on the tick it was pressed.
+    pub kill_all: bool,
+}
+
+/// Inputs of a whole run, enough to play it back when combined with its seed.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub character: String,
+    /// Movement of every fixed tick while playing.
+    pub ticks: Vec<(i8, i8)>,
+    /// Every choice made on the level up screens, in order.
+    pub upgrades: Vec<LevelUpChoice>,
+    /// Shop upgrade levels the run started with.
+    #[serde(default)]
+    pub shop: BTreeMap<String, u32>,
+    /// Every choice made in the campfire shops, in order.
+    #[serde(default)]
+    pub campfires: Vec<CampfireChoice>,
+    /// Ticks the kill all shortcut was pressed on.
+    #[serde(default)]
+    pub kill_all: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LevelUpChoice {
+    Upgrade(usize),
+    Reroll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CampfireChoice {
+    Buff(usize),
+    WeaponLevel(Weapon),
+    Leave,
+}
+
+/// Where `PlayerInput` comes from, selected with `--record <path>` or `--replay <path>`.
+#[derive(Resource, Debug, Default, Clone)]
+pub enum InputSource {
+    #[default]
+    Keyboard,
+    Record {
+        path: PathBuf,
+        replay: Replay,
+    },
+    Playback {
+        replay: Replay,
+        tick: usize,
+        upgrade: usize,
+        campfire: usize,
+    },
+}
+
+impl InputSource {
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let value_of = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .map(PathBuf::from)
+        };
+
+        if let Some(path) = value_of("--replay") {
+            let replay = std::fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|replay| ron::from_str(&replay).map_err(|error| error.to_string()));
+
+            return match replay {
+                Ok(replay) => Self::Playback {
+                    replay,
+                    tick: 0,
+                    upgrade: 0,
+                    campfire: 0,
+                },
+                Err(error) => {
+                    error!("Failed to load replay {}: {error}", path.display());
+                    Self::Keyboard
+                }
+            };
+        }
+
+        match value_of("--record") {
+            Some(path) => Self::Record {
+                path,
+                replay: Replay::default(),
+            },
+            None => Self::Keyboard,
+        }
+    }
+
+    pub fn shop_levels(&self) -> Option<&BTreeMap<String, u32>> {
+        match self {
+            Self::Playback { replay, .. } => Some(&replay.shop),
+            _ => None,
+        }
+    }
+
+    /// Choice to make on the current level up, only known when playing a replay back.
+    pub fn next_upgrade(&mut self) -> Option<LevelUpChoice> {
+        let Self::Playback {
+            replay, upgrade, ..
+        } = self
+        else {
+            return None;
+        };
+
+        let next = replay.upgrades.get(*upgrade).copied();
+        *upgrade += 1;
+        next
+    }
+
+    pub fn record_upgrade(&mut self, choice: LevelUpChoice) {
+        if let Self::Record { replay, .. } = self {
+            replay.upgrades.push(choice);
+        }
+    }
+
+    /// Choice to make in the current campfire shop, only known when playing a replay back.
+    pub fn next_campfire_choice(&mut self) -> Option<CampfireChoice> {
+        let Self::Playback {
+            replay, campfire, ..
+        } = self
+        else {
+            return None;
+        };
+
+        let next = replay.campfires.get(*campfire).copied();
+        *campfire += 1;
+        next
+    }
+
+    pub fn record_campfire_choice(&mut self, choice: CampfireChoice) {
+        if let Self::Record { replay, .. } = self {
+            replay.campfires.push(choice);
+        }
+    }
+}
+
+/// Skips the main menu and starts the recorded run right away.
+fn start_replay(
+    source: Res<InputSource>,
+    mut seed: ResMut<RunSeed>,
+    mut character: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let InputSource::Playback { replay, .. } = source.as_ref() {
+        seed.0 = replay.seed;
+        character.0 = replay.character.clone();
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn start_recording(
+    mut source: ResMut<InputSource>,
+    mut input: ResMut<PlayerInput>,
+    seed: Res<RunSeed>,
+    character: Res<SelectedCharacter>,
+    bank: Res<Bank>,
+) {
+    *input = PlayerInput::default();
+
+    match source.as_mut() {
+        InputSource::Keyboard => {}
+        InputSource::Record { replay, .. } => {
+            *replay = Replay {
+                seed: seed.0,
+                character: character.0.clone(),
+                shop: bank.upgrades.clone(),
+                ..Default::default()
+            }
+        }
+        InputSource::Playback {
+            tick,
+            upgrade,
+            campfire,
+            ..
+        } => {
+            *tick = 0;
+            *upgrade = 0;
+            *campfire = 0;
+        }
+    }
+}
+
+fn sample_player_input(
+    mut source: ResMut<InputSource>,
+    mut input: ResMut<PlayerInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut kill_all_held: Local<bool>,
+) {
+    if let InputSource::Playback { replay, tick, .. } = source.as_mut() {
+        let (x, y) = replay.ticks.get(*tick).copied().unwrap_or_default();
+        input.movement = Vec2::new(x as f32, y as f32);
+        input.kill_all = replay.kill_all.binary_search(tick).is_ok();
+        *tick += 1;
+        return;
+    }
+
+    // A frame may run several ticks or none, so presses are told apart from the key state
+    // between ticks rather than with `just_pressed`
+    let kill_all = keyboard.pressed(KeyCode::Space);
+    input.kill_all = kill_all && !*kill_all_held;
+    *kill_all_held = kill_all;
+
+    let axis = |negative: KeyCode, positive: KeyCode| -> i8 {
+        if keyboard.pressed(positive) {
+            1
+        } else if keyboard.pressed(negative) {
+            -1
+        } else {
+            0
+        }
+    };
+    let (x, y) = (
+        axis(KeyCode::KeyA, KeyCode::KeyD),
+        axis(KeyCode::KeyS, KeyCode::KeyW),
+    );
+    input.movement = Vec2::new(x as f32, y as f32);
+
+    if let InputSource::Record { replay, .. } = source.as_mut() {
+        if input.kill_all {
+            replay.kill_all.push(replay.ticks.len());
+        }
+        replay.ticks.push((x, y));
+    }
+}
+
+fn save_recording(source: Res<InputSource>) {
+    let InputSource::Record { path, replay } = source.as_ref() else {
+        return;
+    };
+
+    let saved = ron::ser::to_string_pretty(replay, Default::default())
+        .map_err(|error| error.to_string())
+        .and_then(|replay| std::fs::write(path, replay).map_err(|error| error.to_string()));
+
+    match saved {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(error) => error!("Failed to save replay to {}: {error}", path.display()),
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedFirst, restore_simulated_transforms)
+            .add_systems(FixedLast, record_simulated_transforms)
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Smooths out the movement of an entity simulated in `FixedUpdate` by drawing it between its
+/// last two simulated transforms, the simulation itself only ever sees the simulated ones.
+#[derive(Component, Debug, Default)]
+pub struct Interpolated {
+    previous: Option<(Vec3, Quat)>,
+    current: Option<(Vec3, Quat)>,
+}
+
+fn restore_simulated_transforms(mut query: Query<(&mut Interpolated, &mut Transform)>) {
+    for (mut interpolated, mut transform) in &mut query {
+        if let Some((translation, rotation)) = interpolated.current {
+            transform.translation = translation;
+            transform.rotation = rotation;
+        }
+        interpolated.previous = interpolated.current;
+    }
+}
+
+fn record_simulated_transforms(mut query: Query<(&mut Interpolated, &Transform)>) {
+    for (mut interpolated, transform) in &mut query {
+        let simulated = Some((transform.translation, transform.rotation));
+        interpolated.current = simulated;
+        if interpolated.previous.is_none() {
+            interpolated.previous = simulated;
+        }
+    }
+}
+
+pub fn interpolate_transforms(
+    mut query: Query<(&Interpolated, &mut Transform)>,
+    time: Res<Time<Fixed>>,
+) {
+    let overstep = time.overstep_fraction();
+    for (interpolated, mut transform) in &mut query {
+        let (Some((previous_translation, previous_rotation)), Some((translation, rotation))) =
+            (interpolated.previous, interpolated.current)
+        else {
+            continue;
+        };
+
+        transform.translation = previous_translation.lerp(translation, overstep);
+        transform.rotation = previous_rotation.slerp(rotation, overstep);
+    }
+}
@@ -0,0 +1,437 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    assets::{ConfigHandles, ProgressionConfig},
+    experience::{Level, LevelUp},
+    input::{InputSource, LevelUpChoice},
+    menu::BUTTON_COLOR,
+    meta::RunBonuses,
+    player::{EquipWeapon, Player, UpgradeWeapon, Weapon, WeaponLevel, MAX_WEAPON_LEVEL},
+    run::InRun,
+    stats::{StatModifier, StatModifiers},
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    GameState,
+};
+
+const UPGRADE_KEYS: [KeyCode; 5] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+];
+
+pub struct LevelUpPlugin;
+
+impl Plugin for LevelUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RerollUpgrades>()
+            .init_resource::<PendingLevelUps>()
+            .init_resource::<UpgradeChoices>()
+            .init_resource::<Rerolls>()
+            .add_systems(OnEnter(InRun), (reset_pending_level_ups, reset_rerolls))
+            .add_systems(
+                FixedUpdate,
+                start_level_up.run_if(in_state(GameState::Playing).and_then(on_event::<LevelUp>())),
+            )
+            .add_systems(Update, capture_rerolls.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_rerolls.in_set(SuspendSystems::Restore))
+            .add_systems(OnEnter(GameState::LevelUp), roll_upgrades)
+            .add_systems(
+                Update,
+                (
+                    choose_upgrade,
+                    roll_upgrades.run_if(on_event::<RerollUpgrades>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::LevelUp)),
+            )
+            .add_systems(OnExit(GameState::LevelUp), despawn_level_up_menu);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upgrade {
+    NewWeapon(Weapon),
+    WeaponLevel(Weapon),
+    StatBoost(StatModifier),
+}
+
+impl Display for Upgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Upgrade::NewWeapon(weapon) => write!(f, "New weapon: {weapon:?}"),
+            Upgrade::WeaponLevel(weapon) => write!(f, "Level up {weapon:?}"),
+            Upgrade::StatBoost(modifier) => write!(f, "{modifier}"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct PendingLevelUps(u32);
+
+#[derive(Resource, Debug, Default)]
+struct UpgradeChoices(Vec<Upgrade>);
+
+#[derive(Resource, Debug, Default)]
+struct Rerolls(u32);
+
+/// Replaces the offered upgrades with a fresh roll while staying in `GameState::LevelUp`.
+#[derive(Event, Debug)]
+struct RerollUpgrades;
+
+#[derive(Component)]
+struct LevelUpMenu;
+
+#[derive(Component)]
+struct UpgradeButton(usize);
+
+#[derive(Component)]
+struct RerollButton;
+
+fn reset_pending_level_ups(mut pending: ResMut<PendingLevelUps>) {
+    pending.0 = 0;
+}
+
+fn reset_rerolls(mut rerolls: ResMut<Rerolls>, bonuses: RunBonuses) {
+    rerolls.0 = bonuses.get().rerolls;
+}
+
+fn capture_rerolls(mut suspended: ResMut<SuspendedRun>, rerolls: Res<Rerolls>) {
+    suspended.rerolls = rerolls.0;
+}
+
+fn restore_rerolls(resumed: Res<ResumedRun>, mut rerolls: ResMut<Rerolls>) {
+    rerolls.0 = resumed.rerolls;
+}
+
+fn start_level_up(
+    mut level_ups: EventReader<LevelUp>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    pending.0 += level_ups.read().count() as u32;
+    next_state.set(GameState::LevelUp);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn roll_upgrades(
+    mut commands: Commands,
+    mut choices: ResMut<UpgradeChoices>,
+    player: Query<(Entity, &Level), With<Player>>,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+    menu: Query<Entity, With<LevelUpMenu>>,
+    config_handles: Res<ConfigHandles>,
+    progression: Res<Assets<ProgressionConfig>>,
+    rerolls: Res<Rerolls>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    let Some(progression) = progression.get(config_handles.progression.id()) else {
+        return error!("Progression config should be loaded at this point");
+    };
+    let (player, level) = player.single();
+
+    let equipped = weapons
+        .iter()
+        .filter(|(_, _, parent)| parent.get() == player)
+        .map(|(weapon, level, _)| (*weapon, level.0))
+        .collect::<Vec<_>>();
+
+    choices.0 = roll_choices(&equipped, progression, &mut *rng);
+
+    for menu in &menu {
+        commands.entity(menu).despawn_recursive();
+    }
+    spawn_level_up_menu(&mut commands, level.0, &choices.0, rerolls.0);
+}
+
+fn roll_choices<R: Rng + ?Sized>(
+    equipped: &[(Weapon, u32)],
+    progression: &ProgressionConfig,
+    rng: &mut R,
+) -> Vec<Upgrade> {
+    upgrade_candidates(equipped, progression)
+        .choose_multiple(rng, progression.upgrade_choices)
+        .copied()
+        .collect()
+}
+
+/// Every upgrade that can still be offered to a player with the `equipped` weapons and levels.
+pub(crate) fn upgrade_candidates(
+    equipped: &[(Weapon, u32)],
+    progression: &ProgressionConfig,
+) -> Vec<Upgrade> {
+    let mut candidates = Weapon::ALL
+        .into_iter()
+        .filter_map(|weapon| {
+            let equipped_level = equipped
+                .iter()
+                .find_map(|(equipped, level)| (*equipped == weapon).then_some(*level));
+
+            match equipped_level {
+                None => Some(Upgrade::NewWeapon(weapon)),
+                Some(level) if level < MAX_WEAPON_LEVEL => Some(Upgrade::WeaponLevel(weapon)),
+                Some(_) => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    candidates.extend(
+        progression
+            .stat_boosts
+            .iter()
+            .copied()
+            .map(Upgrade::StatBoost),
+    );
+    candidates
+}
+
+pub(crate) fn apply_upgrade(
+    commands: &mut Commands,
+    player: Entity,
+    modifiers: &mut StatModifiers,
+    upgrade: Upgrade,
+) {
+    match upgrade {
+        Upgrade::NewWeapon(weapon) => commands.trigger_targets(EquipWeapon(weapon), player),
+        Upgrade::WeaponLevel(weapon) => commands.trigger_targets(UpgradeWeapon(weapon), player),
+        Upgrade::StatBoost(modifier) => modifiers.0.push(modifier),
+    }
+}
+
+fn spawn_level_up_menu(commands: &mut Commands, level: u32, choices: &[Upgrade], rerolls: u32) {
+    commands
+        .spawn((
+            Name::from("Level up menu"),
+            LevelUpMenu,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("Level {level}!"),
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+
+            for (index, upgrade) in choices.iter().enumerate() {
+                parent
+                    .spawn((
+                        UpgradeButton(index),
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            format!("{}. {upgrade}", index + 1),
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+
+            if rerolls > 0 {
+                parent
+                    .spawn((
+                        RerollButton,
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            format!("R. Reroll, {rerolls} left"),
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn choose_upgrade(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    buttons: Query<(&Interaction, &UpgradeButton), Changed<Interaction>>,
+    reroll_buttons: Query<&Interaction, (Changed<Interaction>, With<RerollButton>)>,
+    choices: Res<UpgradeChoices>,
+    mut rerolls: ResMut<Rerolls>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut player: Query<(Entity, &mut StatModifiers), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut reroll: EventWriter<RerollUpgrades>,
+    mut source: ResMut<InputSource>,
+) {
+    let pressed_key = UPGRADE_KEYS
+        .iter()
+        .take(choices.0.len())
+        .position(|key| input.just_pressed(*key));
+    let clicked = buttons.iter().find_map(|(interaction, button)| {
+        (*interaction == Interaction::Pressed).then_some(button.0)
+    });
+    let rerolled = input.just_pressed(KeyCode::KeyR)
+        || reroll_buttons
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed);
+
+    // A replay makes the recorded choice, falling back to the player once it runs out
+    let choice = source.next_upgrade().or_else(|| {
+        if rerolled && rerolls.0 > 0 {
+            Some(LevelUpChoice::Reroll)
+        } else {
+            pressed_key.or(clicked).map(LevelUpChoice::Upgrade)
+        }
+    });
+
+    let index = match choice {
+        Some(LevelUpChoice::Upgrade(index)) => index,
+        Some(LevelUpChoice::Reroll) if rerolls.0 > 0 => {
+            rerolls.0 -= 1;
+            source.record_upgrade(LevelUpChoice::Reroll);
+            reroll.send(RerollUpgrades);
+            return;
+        }
+        _ => return,
+    };
+    let Some(upgrade) = choices.0.get(index) else {
+        return;
+    };
+    source.record_upgrade(LevelUpChoice::Upgrade(index));
+
+    let (player, mut modifiers) = player.single_mut();
+    apply_upgrade(&mut commands, player, &mut modifiers, *upgrade);
+
+    pending.0 = pending.0.saturating_sub(1);
+    if pending.0 > 0 {
+        reroll.send(RerollUpgrades);
+    } else {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn despawn_level_up_menu(mut commands: Commands, menu: Query<Entity, With<LevelUpMenu>>) {
+    for menu in &menu {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        assets::{ChestRewards, ExperienceCurve},
+        stats::{ModifierKind, Stat},
+    };
+
+    fn progression(upgrade_choices: usize) -> ProgressionConfig {
+        ProgressionConfig {
+            experience_curve: ExperienceCurve {
+                base: 5.0,
+                growth: 1.3,
+            },
+            gem_value: 1,
+            elite_gem_value: 10,
+            upgrade_choices,
+            stat_boosts: vec![StatModifier {
+                stat: Stat::Damage,
+                kind: ModifierKind::Multiplicative,
+                value: 0.1,
+            }],
+            chest: ChestRewards {
+                upgrade_chance: 0.5,
+                upgrade_counts: vec![1],
+            },
+        }
+    }
+
+    #[test]
+    fn candidates_level_up_equipped_weapons_and_offer_the_others() {
+        let candidates = upgrade_candidates(
+            &[(Weapon::Orb, 1), (Weapon::Sword, MAX_WEAPON_LEVEL)],
+            &progression(3),
+        );
+
+        assert!(candidates.contains(&Upgrade::WeaponLevel(Weapon::Orb)));
+        assert!(candidates.contains(&Upgrade::NewWeapon(Weapon::Arrow)));
+        assert!(!candidates.contains(&Upgrade::NewWeapon(Weapon::Orb)));
+        assert!(!candidates
+            .iter()
+            .any(|upgrade| *upgrade == Upgrade::WeaponLevel(Weapon::Sword)
+                || *upgrade == Upgrade::NewWeapon(Weapon::Sword)));
+        assert_eq!(
+            candidates
+                .iter()
+                .filter(|upgrade| matches!(upgrade, Upgrade::StatBoost(_)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn rolled_choices_are_distinct_candidates() {
+        let progression = progression(3);
+        let equipped = [(Weapon::Orb, 2)];
+        let candidates = upgrade_candidates(&equipped, &progression);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for _ in 0..50 {
+            let choices = roll_choices(&equipped, &progression, &mut rng);
+            assert_eq!(choices.len(), 3);
+            for (index, choice) in choices.iter().enumerate() {
+                assert!(candidates.contains(choice));
+                assert!(!choices[..index].contains(choice));
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_candidates_than_choices_offers_them_all() {
+        let progression = progression(10);
+        let equipped = [(Weapon::Orb, 1)];
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let choices = roll_choices(&equipped, &progression, &mut rng);
+        assert_eq!(
+            choices.len(),
+            upgrade_candidates(&equipped, &progression).len()
+        );
+    }
+}
@@ -1,9 +1,26 @@
-mod animation;
-mod assets;
-mod common;
-mod enemy;
-mod player;
-mod resources;
+pub mod animation;
+pub mod assets;
+mod campfire;
+mod character;
+mod chest;
+pub mod common;
+pub mod enemy;
+pub mod experience;
+mod hud;
+pub mod input;
+mod interpolation;
+mod level_up;
+mod menu;
+pub mod meta;
+pub mod player;
+pub mod resources;
+pub mod run;
+pub mod save;
+pub mod stats;
+pub mod suspend;
+mod terrain;
+
+use std::path::PathBuf;
 
 use animation::GameAnimationPlugin;
 use avian2d::PhysicsPlugins;
@@ -17,9 +34,30 @@ use bevy_screen_diagnostics::{ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlu
 use bevy_tweening::TweeningPlugin;
 use resources::ResourcePlugin;
 
-use crate::{assets::GameAssetsPlugin, enemy::EnemyPlugin, player::PlayerPlugin};
+use crate::{
+    assets::{ConfigAssetsPlugin, GameAssetsPlugin},
+    campfire::CampfirePlugin,
+    character::CharacterPlugin,
+    chest::ChestPlugin,
+    enemy::EnemyPlugin,
+    experience::ExperiencePlugin,
+    hud::HudPlugin,
+    input::{InputSource, PlayerInputPlugin},
+    interpolation::InterpolationPlugin,
+    level_up::LevelUpPlugin,
+    menu::MenuPlugin,
+    meta::MetaPlugin,
+    player::PlayerPlugin,
+    run::RunPlugin,
+    save::SaveDir,
+    stats::StatsPlugin,
+    suspend::SuspendPlugin,
+    terrain::TerrainPlugin,
+};
 
-pub struct GamePlugin;
+pub struct GamePlugin {
+    pub input_source: InputSource,
+}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
@@ -36,20 +74,18 @@ impl Plugin for GamePlugin {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .init_state::<GameState>()
         .add_plugins((
-            PlayerPlugin,
-            GameAnimationPlugin,
-            EnemyPlugin,
+            GameplayPlugin {
+                save_dir: save::data_dir(),
+                input_source: self.input_source.clone(),
+                ..Default::default()
+            },
             GameAssetsPlugin,
-            ResourcePlugin,
         ))
         .add_plugins((
             WorldInspectorPlugin::default().run_if(input_toggle_active(true, KeyCode::Slash)),
             FrameTimeDiagnosticsPlugin,
-            PhysicsPlugins::default(),
             // PhysicsDebugPlugin::default(),
-            TweeningPlugin,
             ScreenDiagnosticsPlugin::default(),
             ScreenFrameDiagnosticsPlugin,
             HanabiPlugin,
@@ -58,11 +94,67 @@ impl Plugin for GamePlugin {
     }
 }
 
+/// Everything simulating the game without rendering it or loading assets from disk.
+pub struct GameplayPlugin {
+    /// Simulation ticks per second, everything in `FixedUpdate` runs at this rate.
+    pub tick_rate: f64,
+    /// Where the bank and suspended runs are kept between launches, nothing is saved without one.
+    pub save_dir: Option<PathBuf>,
+    /// Keyboard unless a run is being recorded or played back.
+    pub input_source: InputSource,
+}
+
+impl Default for GameplayPlugin {
+    fn default() -> Self {
+        Self {
+            tick_rate: 60.0,
+            save_dir: None,
+            input_source: InputSource::Keyboard,
+        }
+    }
+}
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(self.tick_rate))
+            .insert_resource(SaveDir(self.save_dir.clone()))
+            .insert_resource(self.input_source.clone())
+            .init_state::<GameState>()
+            .add_plugins((
+                PlayerPlugin,
+                GameAnimationPlugin,
+                EnemyPlugin,
+                ConfigAssetsPlugin,
+                ResourcePlugin,
+                ExperiencePlugin,
+                LevelUpPlugin,
+                StatsPlugin,
+                RunPlugin,
+                MenuPlugin,
+                HudPlugin,
+                PlayerInputPlugin,
+                InterpolationPlugin,
+                CharacterPlugin,
+                MetaPlugin,
+            ))
+            .add_plugins((SuspendPlugin, ChestPlugin, CampfirePlugin, TerrainPlugin))
+            .add_plugins((PhysicsPlugins::new(FixedPostUpdate), TweeningPlugin));
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameState {
     #[default]
     AssetLoading,
-    Next,
+    MainMenu,
+    CharacterSelect,
+    Shop,
+    Playing,
+    Paused,
+    LevelUp,
+    Chest,
+    Campfire,
+    GameOver,
 }
 
 fn setup(mut commands: Commands) {
@@ -1,6 +1,10 @@
 use bevy::app::App;
-use noname::GamePlugin;
+use noname::{input::InputSource, GamePlugin};
 
 fn main() {
-    App::new().add_plugins(GamePlugin).run();
+    App::new()
+        .add_plugins(GamePlugin {
+            input_source: InputSource::from_args(),
+        })
+        .run();
 }
@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+
+use crate::{
+    enemy::CurrentWave,
+    resources::Resources,
+    run::{RunSeed, RunStats},
+    save::SaveDir,
+    suspend::{load_suspended_run, ResumeRun, SuspendRun},
+    GameState,
+};
+
+const DIGIT_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+pub const BUTTON_COLOR: Color = Color::srgb(0.2, 0.2, 0.3);
+const HOVERED_BUTTON_COLOR: Color = Color::srgb(0.3, 0.3, 0.45);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(Update, (highlight_buttons, press_menu_buttons))
+            .add_systems(
+                Update,
+                (edit_seed, update_seed_text)
+                    .chain()
+                    .run_if(in_state(GameState::MainMenu)),
+            );
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+enum MenuButton {
+    Continue,
+    Start,
+    Shop,
+    RandomSeed,
+    Restart,
+    Resume,
+    SaveAndQuit,
+    MainMenu,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Continue => "Continue",
+            MenuButton::Start => "Start",
+            MenuButton::Shop => "Shop",
+            MenuButton::RandomSeed => "Random seed",
+            MenuButton::Restart => "Restart",
+            MenuButton::Resume => "Resume",
+            MenuButton::SaveAndQuit => "Save and quit",
+            MenuButton::MainMenu => "Main menu",
+            MenuButton::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Component)]
+struct SeedText;
+
+fn highlight_buttons(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, mut color) in &mut buttons {
+        *color = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON_COLOR.into(),
+            Interaction::None => BUTTON_COLOR.into(),
+        };
+    }
+}
+
+fn press_menu_buttons(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut seed: ResMut<RunSeed>,
+    mut suspend: EventWriter<SuspendRun>,
+    mut resume: EventWriter<ResumeRun>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MenuButton::Continue => {
+                resume.send(ResumeRun);
+            }
+            MenuButton::Start => next_state.set(GameState::CharacterSelect),
+            MenuButton::Shop => next_state.set(GameState::Shop),
+            MenuButton::Resume => next_state.set(GameState::Playing),
+            MenuButton::SaveAndQuit => {
+                suspend.send(SuspendRun);
+            }
+            MenuButton::RandomSeed => seed.roll(),
+            MenuButton::Restart => {
+                seed.roll();
+                next_state.set(GameState::Playing);
+            }
+            MenuButton::MainMenu => next_state.set(GameState::MainMenu),
+            MenuButton::Quit => {
+                exit.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands, seed: Res<RunSeed>, save_dir: Res<SaveDir>) {
+    let buttons = load_suspended_run(&save_dir)
+        .is_some()
+        .then_some(MenuButton::Continue)
+        .into_iter()
+        .chain([
+            MenuButton::Start,
+            MenuButton::Shop,
+            MenuButton::RandomSeed,
+            MenuButton::Quit,
+        ])
+        .collect::<Vec<_>>();
+
+    let lines = spawn_menu(
+        &mut commands,
+        GameState::MainMenu,
+        "NONAME",
+        &[format!("Seed {}", seed.0)],
+        &buttons,
+    );
+    commands.entity(lines[0]).insert(SeedText);
+}
+
+fn edit_seed(input: Res<ButtonInput<KeyCode>>, mut seed: ResMut<RunSeed>) {
+    if input.just_pressed(KeyCode::Backspace) {
+        seed.0 /= 10;
+    }
+
+    if let Some(digit) = DIGIT_KEYS.iter().position(|key| input.just_pressed(*key)) {
+        seed.0 = seed
+            .0
+            .checked_mul(10)
+            .and_then(|seed| seed.checked_add(digit as u64))
+            .unwrap_or(seed.0);
+    }
+}
+
+fn update_seed_text(seed: Res<RunSeed>, mut text: Query<&mut Text, With<SeedText>>) {
+    if !seed.is_changed() {
+        return;
+    }
+
+    for mut text in &mut text {
+        text.sections[0].value = format!("Seed {}", seed.0);
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    spawn_menu(
+        &mut commands,
+        GameState::Paused,
+        "Paused",
+        &[],
+        &[
+            MenuButton::Resume,
+            MenuButton::SaveAndQuit,
+            MenuButton::MainMenu,
+        ],
+    );
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    stats: Res<RunStats>,
+    current_wave: Res<CurrentWave>,
+    resources: Res<Resources>,
+    seed: Res<RunSeed>,
+) {
+    let elapsed = stats.elapsed.as_secs();
+    let lines = [
+        format!("Seed {}", seed.0),
+        format!("Survived {}:{:02}", elapsed / 60, elapsed % 60),
+        format!("Enemies killed {}", stats.kills),
+        format!("Level {}", stats.level),
+        format!("Wave {}", current_wave.0),
+        resources.to_string(),
+    ];
+
+    spawn_menu(
+        &mut commands,
+        GameState::GameOver,
+        "Game over",
+        &lines,
+        &[MenuButton::Restart, MenuButton::MainMenu, MenuButton::Quit],
+    );
+}
+
+/// Spawns a full screen menu despawned automatically once `state` is left, returns the entities
+/// of `lines` in the same order.
+fn spawn_menu(
+    commands: &mut Commands,
+    state: GameState,
+    title: &str,
+    lines: &[String],
+    buttons: &[MenuButton],
+) -> Vec<Entity> {
+    let mut line_entities = Vec::with_capacity(lines.len());
+    commands
+        .spawn((
+            Name::from(format!("{title} menu")),
+            StateScoped(state),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+
+            for line in lines {
+                let line = parent.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font_size: 20.0,
+                        ..Default::default()
+                    },
+                ));
+                line_entities.push(line.id());
+            }
+
+            for button in buttons {
+                parent
+                    .spawn((
+                        *button,
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(240.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+        });
+
+    line_entities
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    assets::{ConfigHandles, ShopConfig, ShopEffect},
+    input::InputSource,
+    menu::BUTTON_COLOR,
+    resources::Resources,
+    run::InRun,
+    save::SaveDir,
+    stats::StatModifier,
+    GameState,
+};
+
+const BANK_FILE: &str = "bank.ron";
+
+pub struct MetaPlugin;
+
+impl Plugin for MetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Bank>()
+            .add_systems(Startup, load_bank)
+            .add_systems(OnExit(InRun), bank_resources)
+            .add_systems(OnEnter(GameState::Shop), spawn_shop_screen)
+            .add_systems(
+                Update,
+                (buy_upgrade, refresh_shop_screen)
+                    .chain()
+                    .run_if(in_state(GameState::Shop)),
+            );
+    }
+}
+
+/// Resources carried over from every run and the shop upgrades bought with them, saved to disk.
+#[derive(Resource, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Bank {
+    pub resources: Resources,
+    /// Level of every shop upgrade bought so far, by name.
+    pub upgrades: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct ShopBonuses {
+    pub modifiers: Vec<StatModifier>,
+    pub weapon_levels: u32,
+    pub rerolls: u32,
+}
+
+impl ShopBonuses {
+    pub fn new(shop: &ShopConfig, levels: &BTreeMap<String, u32>) -> Self {
+        let mut bonuses = Self::default();
+        for (name, level) in levels {
+            let Some(upgrade) = shop.0.get(name) else {
+                warn!("Unknown shop upgrade {name}");
+                continue;
+            };
+
+            let level = (*level).min(upgrade.max_level);
+            match upgrade.effect {
+                ShopEffect::Stat(modifier) => bonuses.modifiers.push(StatModifier {
+                    value: modifier.value * level as f32,
+                    ..modifier
+                }),
+                ShopEffect::StartingWeaponLevel => bonuses.weapon_levels += level,
+                ShopEffect::Reroll => bonuses.rerolls += level,
+            }
+        }
+        bonuses
+    }
+}
+
+/// Shop bonuses of the current run, a replay brings the upgrade levels it was recorded with.
+#[derive(SystemParam)]
+pub struct RunBonuses<'w> {
+    bank: Res<'w, Bank>,
+    source: Res<'w, InputSource>,
+    config_handles: Res<'w, ConfigHandles>,
+    shop: Res<'w, Assets<ShopConfig>>,
+}
+
+impl RunBonuses<'_> {
+    pub fn get(&self) -> ShopBonuses {
+        let Some(shop) = self.shop.get(self.config_handles.shop.id()) else {
+            return ShopBonuses::default();
+        };
+        let levels = self.source.shop_levels().unwrap_or(&self.bank.upgrades);
+        ShopBonuses::new(shop, levels)
+    }
+}
+
+#[derive(Component)]
+struct ShopScreen;
+
+#[derive(Component)]
+struct ShopButton(String);
+
+fn load_bank(save_dir: Res<SaveDir>, mut bank: ResMut<Bank>) {
+    if let Some(saved) = save_dir.load::<Bank>(BANK_FILE) {
+        *bank = saved;
+    }
+}
+
+/// Played back runs were already banked when they were recorded.
+fn bank_resources(
+    resources: Res<Resources>,
+    source: Res<InputSource>,
+    save_dir: Res<SaveDir>,
+    mut bank: ResMut<Bank>,
+) {
+    if matches!(*source, InputSource::Playback { .. }) {
+        return;
+    }
+
+    bank.resources.deposit(&resources);
+    save_dir.save(BANK_FILE, &*bank);
+}
+
+fn spawn_shop_screen(
+    mut commands: Commands,
+    bank: Res<Bank>,
+    config_handles: Res<ConfigHandles>,
+    shop: Res<Assets<ShopConfig>>,
+) {
+    let Some(shop) = shop.get(config_handles.shop.id()) else {
+        return error!("Shop config should be loaded at this point");
+    };
+
+    let mut upgrades = shop.0.iter().collect::<Vec<_>>();
+    upgrades.sort_by_key(|(name, _)| *name);
+
+    commands
+        .spawn((
+            Name::from("Shop"),
+            ShopScreen,
+            StateScoped(GameState::Shop),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..Default::default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Shop",
+                TextStyle {
+                    font_size: 40.0,
+                    ..Default::default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                bank.resources.to_string(),
+                TextStyle {
+                    font_size: 20.0,
+                    ..Default::default()
+                },
+            ));
+
+            for (name, upgrade) in upgrades {
+                let level = bank.upgrades.get(name).copied().unwrap_or_default();
+                let price = if level >= upgrade.max_level {
+                    "maxed".to_string()
+                } else {
+                    upgrade
+                        .cost_at(level)
+                        .iter()
+                        .map(|(resource, amount)| format!("{amount} {resource:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+
+                parent
+                    .spawn((
+                        ShopButton(name.clone()),
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(420.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            format!("{} {level}/{}: {price}", upgrade.title, upgrade.max_level),
+                            TextStyle {
+                                font_size: 24.0,
+                                ..Default::default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn buy_upgrade(
+    buttons: Query<(&Interaction, &ShopButton), Changed<Interaction>>,
+    input: Res<ButtonInput<KeyCode>>,
+    config_handles: Res<ConfigHandles>,
+    shop: Res<Assets<ShopConfig>>,
+    save_dir: Res<SaveDir>,
+    mut bank: ResMut<Bank>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        return next_state.set(GameState::MainMenu);
+    }
+
+    let Some(shop) = shop.get(config_handles.shop.id()) else {
+        return;
+    };
+
+    for (interaction, button) in &buttons {
+        let Some(upgrade) = shop.0.get(&button.0) else {
+            continue;
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let level = bank.upgrades.get(&button.0).copied().unwrap_or_default();
+        if level >= upgrade.max_level
+            || !bank
+                .bypass_change_detection()
+                .resources
+                .try_spend(&upgrade.cost_at(level))
+        {
+            continue;
+        }
+
+        bank.upgrades.insert(button.0.clone(), level + 1);
+        bank.set_changed();
+        save_dir.save(BANK_FILE, &*bank);
+    }
+}
+
+fn refresh_shop_screen(
+    mut commands: Commands,
+    bank: Res<Bank>,
+    screen: Query<(Entity, Ref<ShopScreen>)>,
+    config_handles: Res<ConfigHandles>,
+    shop: Res<Assets<ShopConfig>>,
+) {
+    // A screen spawned since the last check already shows the bank as it is
+    if !bank.is_changed() || screen.iter().any(|(_, screen)| screen.is_added()) {
+        return;
+    }
+
+    for (screen, _) in &screen {
+        commands.entity(screen).despawn_recursive();
+    }
+    spawn_shop_screen(commands, bank, config_handles, shop);
+}
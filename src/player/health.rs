@@ -0,0 +1,104 @@
+use avian2d::prelude::CollidingEntities;
+use bevy::prelude::*;
+
+use crate::{
+    animation::{AnimationState, PlayAnimation},
+    common::Health,
+    enemy::{ContactDamage, Dying, Enemy},
+    stats::StatsSystems,
+    GameState,
+};
+
+use super::Player;
+
+const INVULNERABILITY_SECONDS: f32 = 1.0;
+const BLINK_INTERVAL_SECONDS: f32 = 0.1;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerHit>()
+            .add_event::<PlayerDied>()
+            .add_systems(
+                FixedUpdate,
+                (take_contact_damage, tick_invulnerability)
+                    .chain()
+                    .after(StatsSystems)
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            );
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlayerHit {
+    pub damage: u32,
+}
+
+#[derive(Event, Debug)]
+pub struct PlayerDied;
+
+/// Present on the player for a short while after being hit, enemies can't damage it meanwhile.
+#[derive(Component, Deref, DerefMut)]
+pub struct Invulnerable(Timer);
+
+#[allow(clippy::type_complexity)]
+fn take_contact_damage(
+    mut commands: Commands,
+    mut player: Query<
+        (Entity, &CollidingEntities, &mut Health),
+        (With<Player>, Without<Invulnerable>),
+    >,
+    enemies: Query<&ContactDamage, (With<Enemy>, Without<Dying>)>,
+    mut hits: EventWriter<PlayerHit>,
+    mut deaths: EventWriter<PlayerDied>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok((player, CollidingEntities(collisions), mut health)) = player.get_single_mut() else {
+        return;
+    };
+
+    let Some(damage) = collisions
+        .iter()
+        .filter_map(|entity| enemies.get(*entity).ok())
+        .map(|damage| damage.0)
+        .max()
+    else {
+        return;
+    };
+
+    health.0 = health.0.saturating_sub(damage);
+    hits.send(PlayerHit { damage });
+
+    if health.0 == 0 {
+        commands.trigger_targets(PlayAnimation(AnimationState::Death), player);
+        deaths.send(PlayerDied);
+        next_state.set(GameState::GameOver);
+    } else {
+        commands.trigger_targets(PlayAnimation(AnimationState::Hurt), player);
+        commands
+            .entity(player)
+            .insert(Invulnerable(Timer::from_seconds(
+                INVULNERABILITY_SECONDS,
+                TimerMode::Once,
+            )));
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Invulnerable, &mut Sprite), With<Player>>,
+    time: Res<Time>,
+) {
+    for (player, mut invulnerable, mut sprite) in &mut player {
+        invulnerable.tick(time.delta());
+
+        if invulnerable.finished() {
+            sprite.color.set_alpha(1.0);
+            commands.entity(player).remove::<Invulnerable>();
+        } else {
+            let blink = (invulnerable.elapsed_secs() / BLINK_INTERVAL_SECONDS) as u32 % 2 == 0;
+            sprite.color.set_alpha(if blink { 0.3 } else { 1.0 });
+        }
+    }
+}
@@ -1,20 +1,33 @@
 mod attack;
+mod health;
 mod movement;
 mod spawn;
+mod weapons;
 
 use attack::AttackPlugin;
+use health::HealthPlugin;
 use movement::MovementPlugin;
 use spawn::SpawnPlugin;
+use weapons::WeaponsPlugin;
 
 use bevy::prelude::*;
 
+pub use health::{PlayerDied, PlayerHit};
 pub use movement::{DirectionChanged, MovementDirection};
+pub use spawn::PlayerSnapshot;
+pub use weapons::{EquipWeapon, UpgradeWeapon, Weapon, WeaponLevel, MAX_WEAPON_LEVEL};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((MovementPlugin, SpawnPlugin, AttackPlugin));
+        app.add_plugins((
+            MovementPlugin,
+            SpawnPlugin,
+            AttackPlugin,
+            HealthPlugin,
+            WeaponsPlugin,
+        ));
     }
 }
 
@@ -1,6 +1,9 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, transform::TransformSystem};
 
-use crate::{common::Speed, GameState};
+use crate::{
+    common::Speed, input::PlayerInput, interpolation::interpolate_transforms, stats::StatsSystems,
+    GameState,
+};
 
 use super::Player;
 
@@ -8,43 +11,36 @@ pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_event::<DirectionChanged>().add_systems(
-            Update,
-            move_player.run_if(in_state(GameState::Next).and_then(any_with_component::<Player>)),
-        );
+        app.add_event::<DirectionChanged>()
+            .add_systems(
+                FixedUpdate,
+                move_player
+                    .after(StatsSystems)
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_player
+                    .after(interpolate_transforms)
+                    .before(TransformSystem::TransformPropagate),
+            );
     }
 }
 
 fn move_player(
     mut player: Query<(&mut Transform, &Speed, &mut MovementDirection, Entity), With<Player>>,
-    input: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     time: Res<Time>,
     mut commands: Commands,
 ) {
     let (mut transform, speed, mut old_direction, entity) =
         player.get_single_mut().expect("Player should exist");
 
-    let mut direction = Vec2::ZERO;
-
-    if input.pressed(KeyCode::KeyA) {
-        direction.x = -1.0;
-    }
-
-    if input.pressed(KeyCode::KeyD) {
-        direction.x = 1.0;
-    }
-
-    if input.pressed(KeyCode::KeyS) {
-        direction.y = -1.0;
-    }
-
-    if input.pressed(KeyCode::KeyW) {
-        direction.y = 1.0;
-    }
+    let direction = input.movement;
 
     let new_direction = MovementDirection::from_vec2(direction);
     if *old_direction != new_direction {
-        commands.trigger_targets(DirectionChanged(new_direction.clone()), entity);
+        commands.trigger_targets(DirectionChanged(new_direction), entity);
         *old_direction = new_direction;
     }
 
@@ -52,7 +48,21 @@ fn move_player(
         direction.extend(0.0).normalize_or_zero() * speed.0 * time.delta_seconds();
 }
 
-#[derive(Component, Default, Debug, PartialEq, Clone)]
+fn follow_player(
+    player: Query<&Transform, With<Player>>,
+    mut camera: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    camera.translation = match player.get_single() {
+        Ok(player) => player.translation.truncate().extend(camera.translation.z),
+        Err(_) => Vec3::new(0.0, 0.0, camera.translation.z),
+    };
+}
+
+#[derive(Component, Default, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum MovementDirection {
     Up,
     UpLeft,
@@ -67,6 +77,18 @@ pub enum MovementDirection {
 }
 
 impl MovementDirection {
+    pub const ALL: [Self; 9] = [
+        Self::Up,
+        Self::UpLeft,
+        Self::Left,
+        Self::DownLeft,
+        Self::Idle,
+        Self::Down,
+        Self::DownRight,
+        Self::Right,
+        Self::RightUp,
+    ];
+
     pub fn from_vec2(direction: Vec2) -> Self {
         match direction.as_ref() {
             [0.0, 1.0] => Self::Up,
@@ -80,6 +102,22 @@ impl MovementDirection {
             _ => Self::Idle,
         }
     }
+
+    pub fn to_vec2(&self) -> Option<Vec2> {
+        let direction = match self {
+            Self::Up => Vec2::new(0.0, 1.0),
+            Self::UpLeft => Vec2::new(-1.0, 1.0),
+            Self::Left => Vec2::new(-1.0, 0.0),
+            Self::DownLeft => Vec2::new(-1.0, -1.0),
+            Self::Down => Vec2::new(0.0, -1.0),
+            Self::DownRight => Vec2::new(1.0, -1.0),
+            Self::Right => Vec2::new(1.0, 0.0),
+            Self::RightUp => Vec2::new(1.0, 1.0),
+            Self::Idle => return None,
+        };
+
+        Some(direction.normalize())
+    }
 }
 
 #[derive(Event, Debug)]
@@ -1,20 +1,36 @@
 use avian2d::prelude::Collider;
-use bevy::prelude::*;
-use bevy_spritesheet_animation::{library::AnimationLibrary, prelude::SpritesheetAnimation};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_spritesheet_animation::prelude::SpritesheetAnimation;
 
 use crate::{
-    assets::GameAssetsHandles,
-    common::{Health, Speed},
-    GameState,
+    animation::{AnimationController, AnimationSets},
+    assets::{CharactersConfig, ConfigHandles, GameAssetsHandles},
+    character::SelectedCharacter,
+    common::{Health, MaxHealth, Speed},
+    experience::{Experience, Level},
+    interpolation::Interpolated,
+    meta::RunBonuses,
+    run::InRun,
+    stats::{BaseStats, Stat, StatModifier, StatModifiers, StatsBundle},
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
 };
 
-use super::{movement::MovementDirection, DirectionChanged, Player};
+use super::{
+    movement::MovementDirection,
+    weapons::{EquipWeapon, StartingWeapon, Weapon, WeaponLevel, WeaponLevelBonus},
+    DirectionChanged, Player,
+};
 
 pub struct SpawnPlugin;
 
 impl Plugin for SpawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnExit(GameState::AssetLoading), spawn_player);
+        app.add_systems(OnEnter(InRun), spawn_player)
+            .add_systems(Update, capture_player.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_player.in_set(SuspendSystems::Restore));
+
+        #[cfg(feature = "dev")]
+        app.add_systems(Update, reapply_character_class);
     }
 }
 
@@ -25,30 +41,65 @@ pub struct PlayerBundle {
     speed: Speed,
     sprite_bundle: SpriteBundle,
     health: Health,
+    max_health: MaxHealth,
+    stats: StatsBundle,
     direction: MovementDirection,
     texture_atlas: TextureAtlas,
     sprite_sheet_animation: SpritesheetAnimation,
+    animation_controller: AnimationController,
+    starting_weapon: StartingWeapon,
+    weapon_level_bonus: WeaponLevelBonus,
     collider: Collider,
+    experience: Experience,
+    level: Level,
+    interpolated: Interpolated,
+    state_scoped: StateScoped<InRun>,
 }
 
 fn spawn_player(
     mut commands: Commands,
-    camera: Query<Entity, (With<Camera>, With<Camera2d>)>,
-    animations: Res<AnimationLibrary>,
+    animation_sets: Res<AnimationSets>,
     handles: Res<GameAssetsHandles>,
+    selected: Res<SelectedCharacter>,
+    config_handles: Res<ConfigHandles>,
+    characters: Res<Assets<CharactersConfig>>,
+    bonuses: RunBonuses,
 ) {
-    let camera = camera.single();
-    let Some(sheet_handle) = handles.get_character_sheet_handle("cleric") else {
+    let Some(characters) = characters.get(config_handles.characters.id()) else {
+        return error!("Characters config should be loaded at this point");
+    };
+    let Some(class) = characters.0.get(&selected.0) else {
+        return error!("Unknown character class {}", selected.0);
+    };
+    let Some(sheet_handle) = handles.get_character_sheet_handle(&class.sheet) else {
         panic!("player sheet should be present at this point");
     };
 
-    if let Some(idle_id) = animations.animation_with_name("player_idle") {
+    let Some(set) = animation_sets.characters.get(&class.animations) else {
+        return error!("No animations resolved for {}", class.animations);
+    };
+    let controller = AnimationController::new(set.clone());
+    let bonuses = bonuses.get();
+
+    if let Some(idle_id) = controller.initial_animation() {
         commands
             .spawn(PlayerBundle {
                 name: Name::from("Player"),
                 player: Player,
-                speed: Speed(100.0),
-                health: Health(100),
+                speed: Speed(class.speed),
+                health: Health(class.health),
+                max_health: MaxHealth(class.health),
+                stats: StatsBundle {
+                    base: BaseStats::default()
+                        .with(Stat::MoveSpeed, class.speed)
+                        .with(Stat::MaxHealth, class.health as f32),
+                    modifiers: StatModifiers(
+                        std::iter::once(class.passive)
+                            .chain(bonuses.modifiers)
+                            .collect(),
+                    ),
+                    ..Default::default()
+                },
                 sprite_bundle: SpriteBundle {
                     transform: Transform::from_xyz(0.0, 0.0, 10.0),
                     texture: sheet_handle.clone(),
@@ -57,10 +108,129 @@ fn spawn_player(
                 direction: MovementDirection::default(),
                 texture_atlas: TextureAtlas::from(handles.characters_layouts.clone()),
                 sprite_sheet_animation: SpritesheetAnimation::from_id(idle_id),
+                animation_controller: controller,
+                starting_weapon: StartingWeapon(class.starting_weapon),
+                weapon_level_bonus: WeaponLevelBonus(
+                    [(class.starting_weapon, bonuses.weapon_levels)]
+                        .into_iter()
+                        .collect(),
+                ),
                 collider: Collider::rectangle(30.0, 35.0),
+                experience: Experience::default(),
+                level: Level::default(),
+                interpolated: Interpolated::default(),
+                state_scoped: StateScoped(InRun),
             })
-            .observe(on_player_direction_changed)
-            .add_child(camera);
+            .observe(on_player_direction_changed);
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlayerSnapshot {
+    pub position: Vec2,
+    pub health: u32,
+    pub max_health: u32,
+    pub experience: u32,
+    pub level: u32,
+    /// Class passive first, like on the live player.
+    pub modifiers: Vec<StatModifier>,
+    pub weapons: Vec<(Weapon, u32)>,
+}
+
+#[allow(clippy::type_complexity)]
+fn capture_player(
+    mut suspended: ResMut<SuspendedRun>,
+    player: Query<
+        (
+            Entity,
+            &Transform,
+            &Health,
+            &MaxHealth,
+            &Experience,
+            &Level,
+            &StatModifiers,
+        ),
+        With<Player>,
+    >,
+    weapons: Query<(&Weapon, &WeaponLevel, &Parent)>,
+) {
+    let Ok((entity, transform, health, max_health, experience, level, modifiers)) =
+        player.get_single()
+    else {
+        return error!("Can't suspend a run without a player");
+    };
+
+    suspended.player = PlayerSnapshot {
+        position: transform.translation.truncate(),
+        health: health.0,
+        max_health: max_health.0,
+        experience: experience.0,
+        level: level.0,
+        modifiers: modifiers.0.clone(),
+        weapons: weapons
+            .iter()
+            .filter(|(_, _, parent)| parent.get() == entity)
+            .map(|(weapon, level, _)| (*weapon, level.0))
+            .collect(),
+    };
+}
+
+/// The freshly spawned player gets its saved state back, its weapons are replaced by the saved
+/// ones as they may differ from the starting one.
+#[allow(clippy::type_complexity)]
+fn restore_player(
+    mut commands: Commands,
+    resumed: Res<ResumedRun>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Health,
+            &mut MaxHealth,
+            &mut Experience,
+            &mut Level,
+            &mut StatModifiers,
+        ),
+        With<Player>,
+    >,
+    weapons: Query<(Entity, &Parent), With<Weapon>>,
+) {
+    let Ok((
+        entity,
+        mut transform,
+        mut health,
+        mut max_health,
+        mut experience,
+        mut level,
+        mut modifiers,
+    )) = player.get_single_mut()
+    else {
+        return error!("Player should be spawned before restoring it");
+    };
+    let saved = &resumed.player;
+
+    transform.translation = saved.position.extend(transform.translation.z);
+    // Max health is restored along with the modifiers so recomputing stats doesn't heal
+    health.0 = saved.health;
+    max_health.0 = saved.max_health;
+    experience.0 = saved.experience;
+    level.0 = saved.level;
+    modifiers.0.clone_from(&saved.modifiers);
+
+    for (weapon, parent) in &weapons {
+        if parent.get() == entity {
+            commands.entity(weapon).despawn_recursive();
+        }
+    }
+
+    let bonus = saved
+        .weapons
+        .iter()
+        .map(|(weapon, level)| (*weapon, level.saturating_sub(1)))
+        .collect::<HashMap<_, _>>();
+    commands.entity(entity).insert(WeaponLevelBonus(bonus));
+    for (weapon, _) in &saved.weapons {
+        commands.trigger_targets(EquipWeapon(*weapon), entity);
     }
 }
 
@@ -79,3 +249,38 @@ fn on_player_direction_changed(
         _ => {}
     }
 }
+
+/// The class passive is always the first modifier, the shop and level-up ones come after it.
+#[cfg(feature = "dev")]
+fn reapply_character_class(
+    mut events: EventReader<AssetEvent<CharactersConfig>>,
+    config_handles: Res<ConfigHandles>,
+    characters: Res<Assets<CharactersConfig>>,
+    selected: Res<SelectedCharacter>,
+    mut player: Query<(&mut BaseStats, &mut StatModifiers), With<Player>>,
+) {
+    if events
+        .read()
+        .filter(|event| event.is_modified(config_handles.characters.id()))
+        .count()
+        == 0
+    {
+        return;
+    }
+
+    let Some(class) = characters
+        .get(config_handles.characters.id())
+        .and_then(|characters| characters.0.get(&selected.0))
+    else {
+        return;
+    };
+
+    for (mut base, mut modifiers) in &mut player {
+        *base = BaseStats::default()
+            .with(Stat::MoveSpeed, class.speed)
+            .with(Stat::MaxHealth, class.health as f32);
+        if let Some(passive) = modifiers.0.first_mut() {
+            *passive = class.passive;
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use avian2d::prelude::Collider;
+use bevy::prelude::*;
+use bevy_spatial::{kdtree::KDTree2, SpatialAccess};
+
+use crate::{
+    enemy::{Dying, Enemy, NearestNeighbour},
+    interpolation::Interpolated,
+    player::attack::{DealsDamage, EnemyHit},
+    run::InRun,
+    stats::{Stat, Stats, StatsSystems},
+    GameState,
+};
+
+use super::{Cooldown, RegisterWeapon, Weapon, WeaponLevel, WeaponMesh};
+
+const ARROW_RANGE: f32 = 250.0;
+const ARROW_LIFETIME_SECONDS: f32 = 1.5;
+const ARROW_SPREAD_DEGREES: f32 = 10.0;
+
+pub struct BowPlugin;
+
+impl Plugin for BowPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_weapon(Weapon::Arrow, equip_bow).add_systems(
+            FixedUpdate,
+            (fire_arrow, move_arrows, break_spent_arrows)
+                .after(StatsSystems)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+struct Bow {
+    damage: u32,
+    pierce: u32,
+    speed: f32,
+}
+
+#[derive(Component, Debug)]
+struct Arrow {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+fn equip_bow(In(player): In<Entity>, mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.entity(player).with_children(|parent| {
+        parent.spawn((
+            Name::from("Bow"),
+            Weapon::Arrow,
+            WeaponLevel::default(),
+            Bow {
+                damage: 8,
+                pierce: 1,
+                speed: 400.0,
+            },
+            Cooldown::from_seconds(0.8),
+            WeaponMesh(meshes.add(Rectangle::new(14.0, 3.0))),
+            SpatialBundle::default(),
+        ));
+    });
+}
+
+fn fire_arrow(
+    mut commands: Commands,
+    mut bows: Query<(&Bow, &WeaponLevel, &mut Cooldown, &WeaponMesh, &Parent)>,
+    // The bow sits right on its owner, so shoot from there
+    owners: Query<(&Stats, &Transform)>,
+    targets: Res<KDTree2<NearestNeighbour>>,
+    alive_enemies: Query<(), (With<Enemy>, Without<Dying>)>,
+    time: Res<Time>,
+) {
+    for (bow, level, mut cooldown, mesh, owner) in &mut bows {
+        let Ok((stats, transform)) = owners.get(owner.get()) else {
+            continue;
+        };
+
+        if !cooldown.tick(time.delta(), stats) {
+            continue;
+        }
+
+        let origin = transform.translation.truncate();
+        let mut targets = targets
+            .within_distance(origin, ARROW_RANGE)
+            .into_iter()
+            .filter(|(_, enemy)| enemy.is_some_and(|enemy| alive_enemies.contains(enemy)))
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        targets.sort_by(|a, b| {
+            a.distance_squared(origin)
+                .total_cmp(&b.distance_squared(origin))
+        });
+
+        let Some(nearest) = targets.first().copied() else {
+            continue;
+        };
+
+        // Every arrow goes for a different enemy, the ones left without a target fan out around
+        // the nearest one.
+        let arrows = 1 + stats.get(Stat::Projectiles).floor() as usize;
+        for index in 0..arrows {
+            let direction = match targets.get(index) {
+                Some(target) => (*target - origin).normalize_or_zero(),
+                None => {
+                    let spread =
+                        ARROW_SPREAD_DEGREES.to_radians() * (index - targets.len() + 1) as f32;
+                    let side = if index % 2 == 0 { 1.0 } else { -1.0 };
+                    Vec2::from_angle(spread * side).rotate((nearest - origin).normalize_or_zero())
+                }
+            };
+
+            commands.spawn((
+                Name::from("Arrow"),
+                Arrow {
+                    velocity: direction * bow.speed,
+                    lifetime: Timer::from_seconds(ARROW_LIFETIME_SECONDS, TimerMode::Once),
+                },
+                DealsDamage {
+                    damage: level.scale_damage(bow.damage, stats),
+                    pierce: Some(bow.pierce),
+                },
+                ColorMesh2dBundle {
+                    transform: Transform::from_translation(origin.extend(transform.translation.z))
+                        .with_rotation(Quat::from_rotation_z(direction.to_angle())),
+                    mesh: mesh.clone().into(),
+                    ..Default::default()
+                },
+                Collider::rectangle(14.0, 3.0),
+                Interpolated::default(),
+                StateScoped(InRun),
+            ));
+        }
+    }
+}
+
+fn move_arrows(
+    mut commands: Commands,
+    mut arrows: Query<(Entity, &mut Arrow, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut arrow, mut transform) in &mut arrows {
+        if arrow.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation += arrow.velocity.extend(0.0) * time.delta_seconds();
+    }
+}
+
+fn break_spent_arrows(
+    mut commands: Commands,
+    mut hits: EventReader<EnemyHit>,
+    arrows: Query<&DealsDamage, With<Arrow>>,
+) {
+    for hit in hits.read() {
+        if arrows.get(hit.source).is_ok_and(DealsDamage::is_spent) {
+            commands.entity(hit.source).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,187 @@
+mod bow;
+mod orb;
+mod sword;
+
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemId, prelude::*, utils::HashMap};
+
+use bow::BowPlugin;
+use orb::OrbPlugin;
+use sword::SwordPlugin;
+
+use crate::stats::{Stat, Stats};
+
+use super::Player;
+
+pub const MAX_WEAPON_LEVEL: u32 = 5;
+const MIN_COOLDOWN_MULTIPLIER: f32 = 0.1;
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeaponRegistry>()
+            .add_plugins((OrbPlugin, SwordPlugin, BowPlugin))
+            .add_systems(Update, apply_weapon_level_bonus)
+            .observe(equip_starting_weapons)
+            .observe(on_equip_weapon)
+            .observe(on_upgrade_weapon);
+    }
+}
+
+#[derive(
+    Component, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum Weapon {
+    Orb,
+    Sword,
+    Arrow,
+}
+
+impl Weapon {
+    pub const ALL: [Weapon; 3] = [Weapon::Orb, Weapon::Sword, Weapon::Arrow];
+}
+
+/// Maps every weapon kind to the system equipping it on the entity passed as input.
+#[derive(Resource, Default)]
+pub struct WeaponRegistry(HashMap<Weapon, SystemId<Entity>>);
+
+pub trait RegisterWeapon {
+    fn register_weapon<M>(
+        &mut self,
+        weapon: Weapon,
+        equip: impl IntoSystem<Entity, (), M> + 'static,
+    ) -> &mut Self;
+}
+
+impl RegisterWeapon for App {
+    fn register_weapon<M>(
+        &mut self,
+        weapon: Weapon,
+        equip: impl IntoSystem<Entity, (), M> + 'static,
+    ) -> &mut Self {
+        let equip = self.world_mut().register_system(equip);
+        self.world_mut()
+            .get_resource_or_insert_with(WeaponRegistry::default)
+            .0
+            .insert(weapon, equip);
+        self
+    }
+}
+
+/// Weapon the player gets equipped with as soon as it spawns, the orb if missing.
+#[derive(Component, Debug)]
+pub struct StartingWeapon(pub Weapon);
+
+/// Levels weapons get on top of the first one as soon as they're equipped, every entry is
+/// removed once applied and the component along with the last one.
+#[derive(Component, Debug, Default)]
+pub struct WeaponLevelBonus(pub HashMap<Weapon, u32>);
+
+#[derive(Event, Debug)]
+pub struct EquipWeapon(pub Weapon);
+
+#[derive(Event, Debug)]
+pub struct UpgradeWeapon(pub Weapon);
+
+/// Level of an equipped weapon, starting at 1 and capped at `MAX_WEAPON_LEVEL`.
+#[derive(Component, Debug, Deref)]
+pub struct WeaponLevel(pub u32);
+
+impl Default for WeaponLevel {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl WeaponLevel {
+    /// Every level above the first adds 25% of the base damage, on top of that the owner's
+    /// `Stat::Damage` applies.
+    pub fn scale_damage(&self, damage: u32, stats: &Stats) -> u32 {
+        let level_multiplier = 1.0 + 0.25 * (self.0 - 1) as f32;
+        (damage as f32 * level_multiplier * stats.get(Stat::Damage)).round() as u32
+    }
+}
+
+#[derive(Component, Deref)]
+struct WeaponMesh(Handle<Mesh>);
+
+#[derive(Component)]
+struct Cooldown {
+    base: Duration,
+    timer: Timer,
+}
+
+impl Cooldown {
+    fn from_seconds(seconds: f32) -> Self {
+        Self {
+            base: Duration::from_secs_f32(seconds),
+            timer: Timer::from_seconds(seconds, TimerMode::Repeating),
+        }
+    }
+
+    /// Advances the cooldown shortened by the owner's `Stat::Cooldown`, returns whether the
+    /// weapon should attack this frame.
+    fn tick(&mut self, delta: Duration, stats: &Stats) -> bool {
+        let multiplier = stats.get(Stat::Cooldown).max(MIN_COOLDOWN_MULTIPLIER);
+        self.timer.set_duration(self.base.mul_f32(multiplier));
+        self.timer.tick(delta).just_finished()
+    }
+}
+
+fn equip_starting_weapons(
+    trigger: Trigger<OnAdd, Player>,
+    players: Query<&StartingWeapon>,
+    mut commands: Commands,
+) {
+    let weapon = players
+        .get(trigger.entity())
+        .map_or(Weapon::Orb, |starting| starting.0);
+    commands.trigger_targets(EquipWeapon(weapon), trigger.entity());
+}
+
+fn apply_weapon_level_bonus(
+    mut commands: Commands,
+    mut players: Query<&mut WeaponLevelBonus>,
+    mut weapons: Query<(&Weapon, &Parent, &mut WeaponLevel), Added<WeaponLevel>>,
+) {
+    for (weapon, parent, mut level) in &mut weapons {
+        let Ok(mut bonus) = players.get_mut(parent.get()) else {
+            continue;
+        };
+
+        if let Some(extra) = bonus.0.remove(weapon) {
+            level.0 = (level.0 + extra).min(MAX_WEAPON_LEVEL);
+        }
+        if bonus.0.is_empty() {
+            commands.entity(parent.get()).remove::<WeaponLevelBonus>();
+        }
+    }
+}
+
+fn on_equip_weapon(
+    trigger: Trigger<EquipWeapon>,
+    registry: Res<WeaponRegistry>,
+    mut commands: Commands,
+) {
+    let EquipWeapon(weapon) = trigger.event();
+    match registry.0.get(weapon) {
+        Some(equip) => commands.run_system_with_input(*equip, trigger.entity()),
+        None => error!("No plugin registered for {weapon:?}"),
+    }
+}
+
+fn on_upgrade_weapon(
+    trigger: Trigger<UpgradeWeapon>,
+    mut weapons: Query<(&Weapon, &Parent, &mut WeaponLevel)>,
+) {
+    let UpgradeWeapon(upgraded) = trigger.event();
+    let Some(mut level) = weapons.iter_mut().find_map(|(weapon, parent, level)| {
+        (weapon == upgraded && parent.get() == trigger.entity()).then_some(level)
+    }) else {
+        return error!("Can't upgrade {upgraded:?}, it isn't equipped");
+    };
+
+    level.0 = (level.0 + 1).min(MAX_WEAPON_LEVEL);
+}
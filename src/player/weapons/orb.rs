@@ -0,0 +1,152 @@
+use avian2d::prelude::Collider;
+use bevy::prelude::*;
+use bevy_hanabi::prelude::*;
+
+use crate::{
+    interpolation::Interpolated,
+    player::attack::DealsDamage,
+    stats::{Stats, StatsSystems},
+    GameState,
+};
+
+use super::{RegisterWeapon, Weapon, WeaponLevel};
+
+pub struct OrbPlugin;
+
+impl Plugin for OrbPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_weapon(Weapon::Orb, equip_orb).add_systems(
+            FixedUpdate,
+            (rotate_orb, scale_orb_damage)
+                .after(StatsSystems)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+struct Orb {
+    damage: u32,
+    /// Degrees per second.
+    rotation_speed: f32,
+}
+
+fn equip_orb(
+    In(player): In<Entity>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    // Define a color gradient from red to transparent black
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(1., 0., 0., 1.));
+    gradient.add_key(1.0, Vec4::splat(0.));
+
+    // Create a new expression module
+    let mut module = Module::default();
+
+    // On spawn, randomly initialize the position of the particle
+    // to be over the surface of a sphere of radius 2 units.
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(0.05),
+        dimension: ShapeDimension::Surface,
+    };
+
+    // Also initialize a radial initial velocity to 6 units/sec
+    // away from the (same) sphere center.
+    let init_vel = SetVelocitySphereModifier {
+        center: module.lit(Vec3::ZERO),
+        speed: module.lit(6.),
+    };
+
+    // Initialize the total lifetime of the particle, that is
+    // the time for which it's simulated and rendered. This modifier
+    // is almost always required, otherwise the particles won't show.
+    let lifetime = module.lit(10.); // literal value "10.0"
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+    // Every frame, add a gravity-like acceleration downward
+    let accel = module.lit(Vec3::new(0., -3., 0.));
+    let update_accel = AccelModifier::new(accel);
+
+    // Create the effect asset
+    let effect = EffectAsset::new(
+        // Maximum number of particles alive at a time
+        vec![32768],
+        // Spawn at a rate of 5 particles per second
+        Spawner::rate(100.0.into()),
+        // Move the expression module into the asset
+        module,
+    )
+    .with_name("MyEffect")
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_lifetime)
+    .update(update_accel)
+    // Render the particles with a color gradient over their
+    // lifetime. This maps the gradient key 0 to the particle spawn
+    // time, and the gradient key 1 to the particle death (10s).
+    .render(ColorOverLifetimeModifier { gradient });
+
+    // Insert into the asset system
+    let effect_handle = effects.add(effect);
+
+    commands.entity(player).with_children(|parent| {
+        parent
+            .spawn((
+                Name::from("Orb"),
+                Weapon::Orb,
+                WeaponLevel::default(),
+                Orb {
+                    damage: 10,
+                    rotation_speed: 420.0,
+                },
+                DealsDamage {
+                    damage: 10,
+                    pierce: None,
+                },
+                ColorMesh2dBundle {
+                    // Relative to the player, slightly above it so the orb is drawn on top
+                    transform: Transform::from_xyz(70.0, 0.0, 1.0),
+                    mesh: meshes.add(Circle::new(10.0)).into(),
+                    ..Default::default()
+                },
+                Collider::circle(10.0),
+                Interpolated::default(),
+            ))
+            .with_children(|parent| {
+                parent.spawn(ParticleEffectBundle {
+                    effect: ParticleEffect::new(effect_handle.clone()),
+                    ..Default::default()
+                });
+            });
+    });
+}
+
+fn rotate_orb(mut orbs: Query<(&Orb, &mut Transform)>, time: Res<Time>) {
+    for (orb, mut transform) in &mut orbs {
+        // Vec3::ZERO because point is relative to player
+        transform.rotate_around(
+            Vec3::ZERO,
+            Quat::from_rotation_z(orb.rotation_speed.to_radians() * time.delta_seconds()),
+        );
+    }
+}
+
+fn scale_orb_damage(
+    mut orbs: Query<(&Orb, &WeaponLevel, &Parent, &mut DealsDamage)>,
+    owners: Query<&Stats>,
+) {
+    for (orb, level, owner, mut deals_damage) in &mut orbs {
+        let Ok(stats) = owners.get(owner.get()) else {
+            continue;
+        };
+
+        let damage = level.scale_damage(orb.damage, stats);
+        // Avoid triggering change detection every frame
+        if deals_damage.damage != damage {
+            deals_damage.damage = damage;
+        }
+    }
+}
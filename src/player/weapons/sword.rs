@@ -0,0 +1,122 @@
+use avian2d::prelude::Collider;
+use bevy::prelude::*;
+
+use crate::{
+    animation::{AnimationState, PlayAnimation},
+    player::{attack::DealsDamage, MovementDirection, Player},
+    stats::{Stats, StatsSystems},
+    GameState,
+};
+
+use super::{Cooldown, RegisterWeapon, Weapon, WeaponLevel, WeaponMesh};
+
+const SWORD_REACH: f32 = 40.0;
+const SWORD_SWEEP_DEGREES: f32 = 120.0;
+const SWORD_SWING_SECONDS: f32 = 0.2;
+
+pub struct SwordPlugin;
+
+impl Plugin for SwordPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_weapon(Weapon::Sword, equip_sword).add_systems(
+            FixedUpdate,
+            (swing_sword, sweep_sword_slash)
+                .after(StatsSystems)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+struct Sword {
+    damage: u32,
+    pierce: u32,
+}
+
+#[derive(Component, Debug)]
+struct SwordSlash {
+    lifetime: Timer,
+}
+
+fn equip_sword(In(player): In<Entity>, mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.entity(player).with_children(|parent| {
+        parent.spawn((
+            Name::from("Sword"),
+            Weapon::Sword,
+            WeaponLevel::default(),
+            Sword {
+                damage: 15,
+                pierce: 3,
+            },
+            Cooldown::from_seconds(1.2),
+            WeaponMesh(meshes.add(Rectangle::new(SWORD_REACH, 8.0))),
+            SpatialBundle::default(),
+        ));
+    });
+}
+
+fn swing_sword(
+    mut commands: Commands,
+    mut swords: Query<(Entity, &Sword, &WeaponLevel, &mut Cooldown, &WeaponMesh)>,
+    player: Query<(Entity, &MovementDirection, &Sprite, &Stats), With<Player>>,
+    time: Res<Time>,
+) {
+    let Ok((player, direction, sprite, stats)) = player.get_single() else {
+        return;
+    };
+
+    for (weapon, sword, level, mut cooldown, mesh) in &mut swords {
+        if !cooldown.tick(time.delta(), stats) {
+            continue;
+        }
+
+        // Standing still keeps swinging towards where the player is looking.
+        let facing =
+            direction
+                .to_vec2()
+                .unwrap_or(if sprite.flip_x { Vec2::NEG_X } else { Vec2::X });
+
+        let mut transform = Transform::from_translation(facing.extend(0.0) * SWORD_REACH)
+            .with_rotation(Quat::from_rotation_z(facing.to_angle()));
+        transform.rotate_around(
+            Vec3::ZERO,
+            Quat::from_rotation_z(-SWORD_SWEEP_DEGREES.to_radians() / 2.0),
+        );
+
+        commands.trigger_targets(PlayAnimation(AnimationState::Attack), player);
+        commands.entity(weapon).with_children(|parent| {
+            parent.spawn((
+                Name::from("Sword slash"),
+                SwordSlash {
+                    lifetime: Timer::from_seconds(SWORD_SWING_SECONDS, TimerMode::Once),
+                },
+                DealsDamage {
+                    damage: level.scale_damage(sword.damage, stats),
+                    pierce: Some(sword.pierce),
+                },
+                ColorMesh2dBundle {
+                    transform,
+                    mesh: mesh.clone().into(),
+                    ..Default::default()
+                },
+                Collider::rectangle(SWORD_REACH, 8.0),
+            ));
+        });
+    }
+}
+
+fn sweep_sword_slash(
+    mut commands: Commands,
+    mut slashes: Query<(Entity, &mut SwordSlash, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut slash, mut transform) in &mut slashes {
+        if slash.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let sweep = SWORD_SWEEP_DEGREES.to_radians() * time.delta_seconds() / SWORD_SWING_SECONDS;
+        transform.rotate_around(Vec3::ZERO, Quat::from_rotation_z(sweep));
+    }
+}
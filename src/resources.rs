@@ -1,33 +1,47 @@
-use std::time::Duration;
+use std::{f32::consts::TAU, fmt::Display, time::Duration};
 
 use avian2d::prelude::{Collider, CollidingEntities};
-use bevy::{prelude::*, reflect::Enum};
-use bevy_spritesheet_animation::{
-    events::AnimationEvent, library::AnimationLibrary, prelude::SpritesheetAnimation,
+use bevy::{
+    prelude::*,
+    reflect::{Enum, TypeRegistry},
+    scene::{serde::SceneDeserializer, DynamicEntity},
 };
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+use bevy_spritesheet_animation::{library::AnimationLibrary, prelude::SpritesheetAnimation};
 use bevy_tweening::{
     lens::TransformPositionLens, Animator, EaseFunction, RepeatCount, RepeatStrategy, Tween,
     TweenCompleted,
 };
-use rand::{distributions::Standard, prelude::Distribution};
+use serde::de::DeserializeSeed;
 
 use crate::{
-    assets::GameAssetsHandles,
-    enemy::{Dying, Enemy},
+    assets::{ConfigHandles, DropsConfig, EnemiesConfig, GameAssetsHandles},
+    enemy::EnemyKilled,
     player::Player,
+    run::InRun,
+    stats::{Stat, Stats, StatsSystems},
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
     GameState,
 };
 
-const PICKUP_RANGE: f32 = 70.0;
 pub struct ResourcePlugin;
 
 impl Plugin for ResourcePlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Resource>()
+            .register_type::<Resources>()
+            .register_type::<Amount>()
+            .register_type::<Loot>()
             .add_event::<ResourceCollected>()
             .init_resource::<Resources>()
+            .add_systems(OnEnter(InRun), reset_resources)
+            .add_systems(Update, capture_resources.in_set(SuspendSystems::Capture))
             .add_systems(
-                Update,
+                FixedFirst,
+                restore_resources.in_set(SuspendSystems::Restore),
+            )
+            .add_systems(
+                FixedUpdate,
                 (
                     resource_pickup,
                     on_enemy_killed,
@@ -38,13 +52,15 @@ impl Plugin for ResourcePlugin {
                     )
                         .chain(),
                 )
-                    .run_if(in_state(GameState::Next)),
+                    .after(StatsSystems)
+                    .run_if(in_state(GameState::Playing)),
             )
             .observe(on_resource_collected);
     }
 }
 
-#[derive(Resource, Debug, Default, Reflect)]
+#[derive(Resource, Debug, Default, Clone, Reflect, serde::Serialize, serde::Deserialize)]
+#[reflect(Resource)]
 pub struct Resources {
     gold: u32,
     crystals: u32,
@@ -55,7 +71,84 @@ pub struct Resources {
     gems: u32,
 }
 
-#[derive(Component, Debug, Reflect, Clone)]
+impl Display for Resources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Gold {}, wood {}, ore {}, sulfur {}, mercury {}, crystals {}, gems {}",
+            self.gold, self.wood, self.ore, self.sulfur, self.mercury, self.crystals, self.gems
+        )
+    }
+}
+
+impl Resources {
+    pub fn get(&self, resource: Resource) -> u32 {
+        match resource {
+            Resource::Gold => self.gold,
+            Resource::Crystals => self.crystals,
+            Resource::Mercury => self.mercury,
+            Resource::Sulfur => self.sulfur,
+            Resource::Ore => self.ore,
+            Resource::Wood => self.wood,
+            Resource::Gems => self.gems,
+        }
+    }
+
+    fn get_mut(&mut self, resource: Resource) -> &mut u32 {
+        match resource {
+            Resource::Gold => &mut self.gold,
+            Resource::Crystals => &mut self.crystals,
+            Resource::Mercury => &mut self.mercury,
+            Resource::Sulfur => &mut self.sulfur,
+            Resource::Ore => &mut self.ore,
+            Resource::Wood => &mut self.wood,
+            Resource::Gems => &mut self.gems,
+        }
+    }
+
+    pub fn add(&mut self, resource: Resource, amount: u32) {
+        *self.get_mut(resource) += amount;
+    }
+
+    pub fn deposit(&mut self, other: &Resources) {
+        for resource in Resource::ALL {
+            self.add(resource, other.get(resource));
+        }
+    }
+
+    /// Takes every listed amount out, or nothing at all if any of them isn't covered.
+    pub fn try_spend(&mut self, cost: &[(Resource, u32)]) -> bool {
+        if cost
+            .iter()
+            .any(|(resource, amount)| self.get(*resource) < *amount)
+        {
+            return false;
+        }
+
+        for (resource, amount) in cost {
+            *self.get_mut(*resource) -= amount;
+        }
+        true
+    }
+
+    pub fn total(&self) -> u32 {
+        Resource::ALL
+            .into_iter()
+            .map(|resource| self.get(resource))
+            .sum()
+    }
+}
+
+pub const CHEST_ANIMATION: &str = "treasure_blink";
+
+/// Drops of the same kill are spread this far around where the enemy died.
+const DROP_SPREAD: f32 = 12.0;
+
+fn reset_resources(mut resources: ResMut<Resources>) {
+    *resources = Resources::default();
+}
+
+#[derive(Debug, Reflect, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Resource {
     Gold,
     Crystals,
@@ -66,20 +159,67 @@ pub enum Resource {
     Gems,
 }
 
-impl Distribution<Resource> for Standard {
-    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Resource {
-        match rng.gen_range(0.0..=1.0) {
-            0.0..0.2 => Resource::Gold,
-            0.2..0.4 => Resource::Wood,
-            0.4..0.6 => Resource::Sulfur,
-            0.6..0.8 => Resource::Ore,
-            0.8..0.9 => Resource::Mercury,
-            0.9..0.95 => Resource::Crystals,
-            _ => Resource::Gems,
+impl Resource {
+    pub const ALL: [Resource; 7] = [
+        Resource::Gold,
+        Resource::Wood,
+        Resource::Ore,
+        Resource::Sulfur,
+        Resource::Mercury,
+        Resource::Crystals,
+        Resource::Gems,
+    ];
+
+    /// Index of a still frame in `resources_layout`, used wherever the resource isn't animated.
+    pub fn icon_index(&self) -> usize {
+        match self {
+            Resource::Gems => 10,
+            Resource::Gold => 20,
+            Resource::Crystals => 30,
+            Resource::Mercury => 40,
+            Resource::Ore => 41,
+            Resource::Wood => 42,
+            Resource::Sulfur => 43,
+        }
+    }
+
+    pub fn animation_name(&self) -> &'static str {
+        match self {
+            Resource::Gold => "gold_blink",
+            Resource::Crystals => "crystals_blink",
+            Resource::Gems => "gems_blink",
+            Resource::Mercury => "mercury",
+            Resource::Sulfur => "sulfur",
+            Resource::Ore => "ore",
+            Resource::Wood => "wood",
+        }
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Pickup;
+
+#[derive(Component, Debug, Clone, PartialEq, Reflect, serde::Deserialize)]
+#[reflect(Component)]
+pub enum Loot {
+    Resource(Resource),
+    /// Opens a reward reveal, the named `DropsConfig` table is one of the possible rewards.
+    Chest(String),
+}
+
+impl Loot {
+    fn animation_name(&self) -> &'static str {
+        match self {
+            Loot::Resource(resource) => resource.animation_name(),
+            Loot::Chest(_) => CHEST_ANIMATION,
         }
     }
 }
 
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Amount(pub u32);
+
 #[derive(Component)]
 struct CloseToPlayer;
 
@@ -89,19 +229,17 @@ struct FollowingPlayer;
 #[allow(clippy::type_complexity)]
 fn mark_resource_as_close(
     mut commands: Commands,
-    player: Query<&Transform, With<Player>>,
-    resources: Query<
-        (Entity, &Transform),
-        (Without<Player>, With<Resource>, Without<CloseToPlayer>),
-    >,
+    player: Query<(&Transform, &Stats), With<Player>>,
+    resources: Query<(Entity, &Transform), (Without<Player>, With<Pickup>, Without<CloseToPlayer>)>,
 ) {
-    let player_transform = player.single();
+    let (player_transform, stats) = player.single();
+    let pickup_range = stats.get(Stat::PickupRange);
 
     for (resource, resource_transofrm) in &resources {
         if resource_transofrm
             .translation
             .distance(player_transform.translation)
-            < PICKUP_RANGE
+            < pickup_range
         {
             let tween_direction =
                 player_transform.looking_at(resource_transofrm.translation, Vec3::Y);
@@ -130,14 +268,7 @@ fn mark_resource_as_close(
 fn mark_resource_as_following(
     mut commands: Commands,
     mut completed_tweens: EventReader<TweenCompleted>,
-    resources: Query<
-        Entity,
-        (
-            With<Resource>,
-            With<CloseToPlayer>,
-            Without<FollowingPlayer>,
-        ),
-    >,
+    resources: Query<Entity, (With<Pickup>, With<CloseToPlayer>, Without<FollowingPlayer>)>,
 ) {
     for tween in &mut completed_tweens.read() {
         if let Ok(tween) = resources.get(tween.entity) {
@@ -147,13 +278,13 @@ fn mark_resource_as_following(
 }
 
 fn update_resource_position(
-    player: Query<&GlobalTransform, With<Player>>,
-    mut resources: Query<&mut Transform, (With<Resource>, With<FollowingPlayer>)>,
+    player: Query<&Transform, (With<Player>, Without<Pickup>)>,
+    mut resources: Query<&mut Transform, (With<Pickup>, With<FollowingPlayer>)>,
     time: Res<Time>,
 ) {
     let player_transform = player.single();
     for mut transform in &mut resources {
-        let direction = transform.looking_at(player_transform.translation(), Vec3::Y);
+        let direction = transform.looking_at(player_transform.translation, Vec3::Y);
         transform.translation += direction.forward() * time.delta_seconds() * 250.0;
     }
 }
@@ -161,33 +292,34 @@ fn update_resource_position(
 #[derive(Bundle, Debug)]
 pub struct ResourceBundle {
     name: Name,
-    resource: Resource,
+    loot: Loot,
+    amount: Amount,
+    pickup: Pickup,
     sprite_bundle: SpriteBundle,
     texture_atlas: TextureAtlas,
     sprite_sheet_animation: SpritesheetAnimation,
     collider: Collider,
+    state_scoped: StateScoped<InRun>,
 }
 
 impl ResourceBundle {
     fn new(
-        resource: Resource,
+        loot: Loot,
+        amount: u32,
         handles: &GameAssetsHandles,
         animations: &AnimationLibrary,
         translation: Vec3,
     ) -> Option<Self> {
-        let animation_id = match resource {
-            Resource::Gold => animations.animation_with_name("gold_blink")?,
-            Resource::Crystals => animations.animation_with_name("crystals_blink")?,
-            Resource::Gems => animations.animation_with_name("gems_blink")?,
-            Resource::Mercury => animations.animation_with_name("mercury")?,
-            Resource::Sulfur => animations.animation_with_name("sulfur")?,
-            Resource::Ore => animations.animation_with_name("ore")?,
-            Resource::Wood => animations.animation_with_name("wood")?,
-        };
+        let animation_id = animations.animation_with_name(loot.animation_name())?;
 
         Some(Self {
-            name: Name::from(resource.variant_name()),
-            resource,
+            name: Name::from(match &loot {
+                Loot::Resource(resource) => resource.variant_name(),
+                Loot::Chest(_) => "Chest",
+            }),
+            loot,
+            amount: Amount(amount),
+            pickup: Pickup,
             sprite_bundle: SpriteBundle {
                 texture: handles.resources.clone(),
                 transform: Transform::from_translation(translation),
@@ -196,28 +328,29 @@ impl ResourceBundle {
             texture_atlas: TextureAtlas::from(handles.resources_layout.clone()),
             sprite_sheet_animation: SpritesheetAnimation::from_id(animation_id),
             collider: Collider::rectangle(15.0, 15.0),
+            state_scoped: StateScoped(InRun),
         })
     }
 }
 
 #[derive(Event)]
 pub struct ResourceCollected {
-    resource: Resource,
-    amount: u32,
+    pub loot: Loot,
+    pub amount: u32,
 }
 
 fn resource_pickup(
     mut commands: Commands,
     collisions: Query<&CollidingEntities, With<Player>>,
-    resources: Query<(Entity, &Resource), Without<Player>>,
+    resources: Query<(Entity, &Loot, &Amount), Without<Player>>,
 ) {
     for CollidingEntities(collisions) in collisions.iter() {
-        for (entity, resource) in &resources {
+        for (entity, loot, amount) in &resources {
             if collisions.contains(&entity) {
                 commands.trigger_targets(
                     ResourceCollected {
-                        resource: resource.clone(),
-                        amount: 1,
+                        loot: loot.clone(),
+                        amount: amount.0,
                     },
                     entity,
                 );
@@ -232,52 +365,162 @@ fn on_resource_collected(
     mut commands: Commands,
 ) {
     let event = trigger.event();
-    match event.resource {
-        Resource::Gold => resources.gold += event.amount,
-        Resource::Crystals => resources.crystals += event.amount,
-        Resource::Mercury => resources.mercury += event.amount,
-        Resource::Sulfur => resources.sulfur += event.amount,
-        Resource::Ore => resources.ore += event.amount,
-        Resource::Wood => resources.wood += event.amount,
-        Resource::Gems => resources.gems += event.amount,
+    if let Loot::Resource(resource) = event.loot {
+        resources.add(resource, event.amount);
     }
 
     commands.entity(trigger.entity()).despawn_recursive();
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 fn on_enemy_killed(
     mut commands: Commands,
-    mut animation_events: EventReader<AnimationEvent>,
+    mut killed: EventReader<EnemyKilled>,
+    player: Query<&Stats, With<Player>>,
     handles: Res<GameAssetsHandles>,
     animations: Res<AnimationLibrary>,
-    dying_enemies: Query<(Entity, &GlobalTransform), (With<Enemy>, With<Dying>)>,
+    config_handles: Res<ConfigHandles>,
+    enemies: Res<Assets<EnemiesConfig>>,
+    drops: Res<Assets<DropsConfig>>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
 ) {
-    for animation_event in animation_events.read() {
-        if let AnimationEvent::AnimationRepetitionEnd {
-            animation_repetition,
-            entity,
-            ..
-        } = animation_event
-        {
-            let Ok(killed_enemy) = dying_enemies.get(*entity) else {
-                return;
-            };
-
-            if animation_repetition == &1 {
-                let resource: Resource = rand::random();
-
-                let Some(bundle) = ResourceBundle::new(
-                    resource,
-                    &handles,
-                    &animations,
-                    killed_enemy.1.translation(),
-                ) else {
-                    return error!("Failed to create resource bundle");
-                };
-
-                commands.spawn((bundle,));
+    if killed.is_empty() {
+        return;
+    }
+
+    let (Some(enemies), Some(drops)) = (
+        enemies.get(config_handles.enemies.id()),
+        drops.get(config_handles.drops.id()),
+    ) else {
+        return error!("Enemies and drops configs should be loaded at this point");
+    };
+    let luck = player
+        .get_single()
+        .map_or(0.0, |stats| stats.get(Stat::Luck));
+
+    for killed_enemy in killed.read() {
+        let Some(table_name) = enemies
+            .0
+            .get(&killed_enemy.archetype)
+            .map(|archetype| archetype.drop_table(killed_enemy.elite))
+        else {
+            error!("Unknown enemy archetype {}", killed_enemy.archetype);
+            continue;
+        };
+        let Some(table) = drops.0.get(table_name) else {
+            error!("Unknown drop table {table_name}");
+            continue;
+        };
+
+        spawn_drops(
+            &mut commands,
+            table.roll(luck, &mut *rng),
+            killed_enemy.position,
+            &handles,
+            &animations,
+        );
+    }
+}
+
+pub(crate) fn spawn_drops(
+    commands: &mut Commands,
+    drops: Vec<(Loot, u32)>,
+    position: Vec3,
+    handles: &GameAssetsHandles,
+    animations: &AnimationLibrary,
+) {
+    let count = drops.len();
+    for (index, (loot, amount)) in drops.into_iter().enumerate() {
+        let offset = if count > 1 {
+            Vec2::from_angle(TAU * index as f32 / count as f32) * DROP_SPREAD
+        } else {
+            Vec2::ZERO
+        };
+
+        match ResourceBundle::new(
+            loot,
+            amount,
+            handles,
+            animations,
+            position + offset.extend(0.0),
+        ) {
+            Some(bundle) => {
+                commands.spawn(bundle);
             }
+            None => error!("Failed to create resource bundle"),
         }
     }
 }
+
+/// Collected resources and the drops lying on the ground go through reflection, the scene only
+/// keeps their `Loot`, `Amount` and `Transform`, everything else is rebuilt by `ResourceBundle`.
+fn capture_resources(world: &mut World) {
+    let mut drops = world.query_filtered::<Entity, With<Loot>>();
+    let drops = drops.iter(world).collect::<Vec<_>>();
+
+    let scene = DynamicSceneBuilder::from_world(world)
+        .deny_all()
+        .allow::<Loot>()
+        .allow::<Amount>()
+        .allow::<Transform>()
+        .deny_all_resources()
+        .allow_resource::<Resources>()
+        .extract_entities(drops.into_iter())
+        .extract_resources()
+        .build();
+
+    let serialized = scene.serialize(&world.resource::<AppTypeRegistry>().read());
+    match serialized {
+        Ok(serialized) => world.resource_mut::<SuspendedRun>().scene = serialized,
+        Err(error) => error!("Failed to serialize resources: {error}"),
+    }
+}
+
+fn restore_resources(world: &mut World) {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let scene = match deserialize_scene(&world.resource::<ResumedRun>().scene, &registry.read()) {
+        Ok(scene) => scene,
+        Err(error) => return error!("Failed to deserialize resources: {error}"),
+    };
+
+    if let Some(resources) = scene
+        .resources
+        .iter()
+        .find_map(|resource| Resources::from_reflect(resource.as_ref()))
+    {
+        *world.resource_mut::<Resources>() = resources;
+    }
+
+    let bundles = {
+        let handles = world.resource::<GameAssetsHandles>();
+        let animations = world.resource::<AnimationLibrary>();
+        scene
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let loot = reflected::<Loot>(entity)?;
+                let amount = reflected::<Amount>(entity).map_or(1, |amount| amount.0);
+                let transform = reflected::<Transform>(entity)?;
+                ResourceBundle::new(loot, amount, handles, animations, transform.translation)
+            })
+            .collect::<Vec<_>>()
+    };
+    world.spawn_batch(bundles);
+}
+
+fn reflected<T: FromReflect>(entity: &DynamicEntity) -> Option<T> {
+    entity
+        .components
+        .iter()
+        .find_map(|component| T::from_reflect(component.as_ref()))
+}
+
+fn deserialize_scene(scene: &str, registry: &TypeRegistry) -> Result<DynamicScene, String> {
+    let mut deserializer =
+        ron::de::Deserializer::from_str(scene).map_err(|error| error.to_string())?;
+    SceneDeserializer {
+        type_registry: registry,
+    }
+    .deserialize(&mut deserializer)
+    .map_err(|error| error.to_string())
+}
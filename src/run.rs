@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, EntropyPlugin, GlobalEntropy};
+use rand::SeedableRng;
+
+use crate::{
+    character::SelectedCharacter,
+    enemy::EnemyKilled,
+    experience::LevelUp,
+    suspend::{ResumedRun, SuspendSystems, SuspendedRun},
+    GameState,
+};
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
+            .add_computed_state::<InRun>()
+            .enable_state_scoped_entities::<GameState>()
+            .enable_state_scoped_entities::<InRun>()
+            .init_resource::<RunStats>()
+            .init_resource::<RunSeed>()
+            .add_systems(OnEnter(GameState::MainMenu), roll_seed)
+            .add_systems(OnEnter(InRun), (seed_rng, reset_run_stats))
+            .add_systems(
+                FixedUpdate,
+                track_run_stats.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(FixedLast, skip_ticks_after_transition)
+            .add_systems(
+                Update,
+                toggle_pause
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+            )
+            .add_systems(Update, capture_run.in_set(SuspendSystems::Capture))
+            .add_systems(FixedFirst, restore_run.in_set(SuspendSystems::Restore))
+            .add_systems(OnEnter(GameState::Paused), pause_time)
+            .add_systems(OnExit(GameState::Paused), unpause_time)
+            .add_systems(OnEnter(GameState::LevelUp), pause_time)
+            .add_systems(OnExit(GameState::LevelUp), unpause_time)
+            .add_systems(OnEnter(GameState::Chest), pause_time)
+            .add_systems(OnExit(GameState::Chest), unpause_time)
+            .add_systems(OnEnter(GameState::Campfire), pause_time)
+            .add_systems(OnExit(GameState::Campfire), unpause_time);
+    }
+}
+
+/// Present while a run is going on, no matter if it's paused or waiting for a level up choice.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct InRun;
+
+impl ComputedStates for InRun {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        match sources {
+            GameState::Playing
+            | GameState::Paused
+            | GameState::LevelUp
+            | GameState::Chest
+            | GameState::Campfire => Some(InRun),
+            _ => None,
+        }
+    }
+}
+
+/// Seed of every gameplay random number of a run.
+#[derive(Resource, Debug, Clone, Copy, Deref, DerefMut)]
+pub struct RunSeed(pub u64);
+
+impl Default for RunSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl RunSeed {
+    pub fn roll(&mut self) {
+        self.0 = rand::random();
+    }
+}
+
+fn roll_seed(mut seed: ResMut<RunSeed>) {
+    seed.roll();
+}
+
+fn seed_rng(seed: Res<RunSeed>, mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>) {
+    info!("Starting run with seed {}", seed.0);
+    *rng = GlobalEntropy::seed_from_u64(seed.0);
+}
+
+/// States only change between frames, so once a tick leaves `Playing` the ticks left in the
+/// frame are dropped instead of simulating past it, and a replay stops on the same tick as its
+/// recording whatever the frame timing.
+fn skip_ticks_after_transition(
+    next_state: Res<NextState<GameState>>,
+    mut time: ResMut<Time<Fixed>>,
+) {
+    if matches!(*next_state, NextState::Pending(_)) {
+        let overstep = time.overstep();
+        time.discard_overstep(overstep);
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RunStats {
+    pub elapsed: Duration,
+    pub kills: u32,
+    pub level: u32,
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats {
+        level: 1,
+        ..Default::default()
+    };
+}
+
+fn track_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut killed: EventReader<EnemyKilled>,
+    mut level_ups: EventReader<LevelUp>,
+    time: Res<Time>,
+) {
+    stats.elapsed += time.delta();
+    stats.kills += killed.read().count() as u32;
+
+    if let Some(level_up) = level_ups.read().last() {
+        stats.level = level_up.level;
+    }
+}
+
+fn capture_run(
+    mut suspended: ResMut<SuspendedRun>,
+    seed: Res<RunSeed>,
+    character: Res<SelectedCharacter>,
+    stats: Res<RunStats>,
+    rng: Res<GlobalEntropy<ChaCha8Rng>>,
+) {
+    suspended.seed = seed.0;
+    suspended.character = character.0.clone();
+    suspended.stats = stats.clone();
+    suspended.rng = Some(rng.clone());
+}
+
+fn restore_run(
+    resumed: Res<ResumedRun>,
+    mut stats: ResMut<RunStats>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    *stats = resumed.stats.clone();
+    if let Some(saved) = &resumed.rng {
+        *rng = saved.clone();
+    }
+}
+
+fn toggle_pause(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
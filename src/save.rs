@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Directory files persisted between launches are kept in, nothing gets saved without one.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SaveDir(pub Option<PathBuf>);
+
+impl SaveDir {
+    /// `None` when there's no save directory or the file doesn't exist yet.
+    pub fn load<T: DeserializeOwned>(&self, file: &str) -> Option<T> {
+        let path = self.0.as_ref()?.join(file);
+        let content = std::fs::read_to_string(&path).ok()?;
+        ron::from_str(&content)
+            .map_err(|error| error!("Failed to read {}: {error}", path.display()))
+            .ok()
+    }
+
+    pub fn exists(&self, file: &str) -> bool {
+        self.0.as_ref().is_some_and(|dir| dir.join(file).is_file())
+    }
+
+    pub fn save<T: Serialize>(&self, file: &str, value: &T) {
+        let Some(dir) = &self.0 else {
+            return;
+        };
+        let path = dir.join(file);
+
+        let saved = ron::ser::to_string_pretty(value, Default::default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                std::fs::create_dir_all(dir)
+                    .and_then(|_| std::fs::write(&path, content))
+                    .map_err(|error| error.to_string())
+            });
+
+        if let Err(error) = saved {
+            error!("Failed to save {}: {error}", path.display());
+        }
+    }
+
+    pub fn remove(&self, file: &str) {
+        let Some(dir) = &self.0 else {
+            return;
+        };
+        let path = dir.join(file);
+
+        if let Err(error) = std::fs::remove_file(&path) {
+            error!("Failed to remove {}: {error}", path.display());
+        }
+    }
+}
+
+/// Per-user data directory of the platform, e.g. `~/.local/share/noname` on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+        })
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+
+    base.map(|base| base.join("noname"))
+}
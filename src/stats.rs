@@ -0,0 +1,226 @@
+use std::fmt::Display;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    common::{Health, MaxHealth, Speed},
+    player::Player,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (recompute_stats, apply_player_stats)
+                .chain()
+                .in_set(StatsSystems),
+        );
+    }
+}
+
+/// Recomputes `Stats` during the tick, after the systems changing `StatModifiers` and before the
+/// ones reading stats.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StatsSystems;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Stat {
+    MoveSpeed,
+    MaxHealth,
+    PickupRange,
+    /// Multiplier applied to the damage of every weapon.
+    Damage,
+    /// Multiplier applied to the cooldown of every weapon.
+    Cooldown,
+    /// Extra projectiles fired by every ranged weapon.
+    Projectiles,
+    /// Makes enemies less likely to drop nothing, see `DropTable::roll`.
+    Luck,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 7] = [
+        Stat::MoveSpeed,
+        Stat::MaxHealth,
+        Stat::PickupRange,
+        Stat::Damage,
+        Stat::Cooldown,
+        Stat::Projectiles,
+        Stat::Luck,
+    ];
+
+    fn base(&self) -> f32 {
+        match self {
+            Stat::MoveSpeed => 100.0,
+            Stat::MaxHealth => 100.0,
+            Stat::PickupRange => 70.0,
+            Stat::Damage => 1.0,
+            Stat::Cooldown => 1.0,
+            Stat::Projectiles => 0.0,
+            Stat::Luck => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ModifierKind {
+    /// Added to the base value.
+    Additive,
+    /// Fraction of the base value, e.g. `0.1` is +10%.
+    Multiplicative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub kind: ModifierKind,
+    pub value: f32,
+}
+
+impl Display for StatModifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stat = match self.stat {
+            Stat::MoveSpeed => "move speed",
+            Stat::MaxHealth => "max health",
+            Stat::PickupRange => "pickup range",
+            Stat::Damage => "damage",
+            Stat::Cooldown => "cooldown",
+            Stat::Projectiles => "projectiles",
+            Stat::Luck => "luck",
+        };
+
+        match self.kind {
+            ModifierKind::Additive => write!(f, "{:+} {stat}", self.value),
+            ModifierKind::Multiplicative => write!(f, "{:+.0}% {stat}", self.value * 100.0),
+        }
+    }
+}
+
+/// Stat values before any modifiers, anything missing falls back to `Stat::base`.
+#[derive(Component, Debug, Default, Clone)]
+pub struct BaseStats(HashMap<Stat, f32>);
+
+impl BaseStats {
+    pub fn with(mut self, stat: Stat, value: f32) -> Self {
+        self.0.insert(stat, value);
+        self
+    }
+
+    fn get(&self, stat: Stat) -> f32 {
+        self.0.get(&stat).copied().unwrap_or_else(|| stat.base())
+    }
+}
+
+#[derive(Component, Debug, Default, Clone)]
+pub struct StatModifiers(pub Vec<StatModifier>);
+
+#[derive(Component, Debug, Default)]
+pub struct Stats(HashMap<Stat, f32>);
+
+impl Stats {
+    fn new(base: &BaseStats, modifiers: &StatModifiers) -> Self {
+        let stats = Stat::ALL.into_iter().map(|stat| {
+            let (additive, multiplicative) = modifiers
+                .0
+                .iter()
+                .filter(|modifier| modifier.stat == stat)
+                .fold(
+                    (0.0, 0.0),
+                    |(additive, multiplicative), modifier| match modifier.kind {
+                        ModifierKind::Additive => (additive + modifier.value, multiplicative),
+                        ModifierKind::Multiplicative => (additive, multiplicative + modifier.value),
+                    },
+                );
+
+            let value = (base.get(stat) + additive) * (1.0 + multiplicative);
+            (stat, value.max(0.0))
+        });
+        Self(stats.collect())
+    }
+
+    pub fn get(&self, stat: Stat) -> f32 {
+        self.0.get(&stat).copied().unwrap_or_else(|| stat.base())
+    }
+}
+
+#[derive(Bundle, Debug, Default)]
+pub struct StatsBundle {
+    pub base: BaseStats,
+    pub modifiers: StatModifiers,
+    pub stats: Stats,
+}
+
+#[allow(clippy::type_complexity)]
+fn recompute_stats(
+    mut query: Query<
+        (&BaseStats, &StatModifiers, &mut Stats),
+        Or<(Changed<BaseStats>, Changed<StatModifiers>)>,
+    >,
+) {
+    for (base, modifiers, mut stats) in &mut query {
+        *stats = Stats::new(base, modifiers);
+    }
+}
+
+fn apply_player_stats(
+    mut player: Query<
+        (&Stats, &mut Speed, &mut Health, &mut MaxHealth),
+        (With<Player>, Changed<Stats>),
+    >,
+) {
+    for (stats, mut speed, mut health, mut max_health) in &mut player {
+        speed.0 = stats.get(Stat::MoveSpeed);
+
+        let new_max_health = stats.get(Stat::MaxHealth).round() as u32;
+        // Raising max health also heals by the same amount, lowering it only clamps
+        health.0 = (health.0 + new_max_health.saturating_sub(max_health.0)).min(new_max_health);
+        max_health.0 = new_max_health;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifier(stat: Stat, kind: ModifierKind, value: f32) -> StatModifier {
+        StatModifier { stat, kind, value }
+    }
+
+    #[test]
+    fn missing_stats_fall_back_to_their_base() {
+        let stats = Stats::new(&BaseStats::default(), &StatModifiers::default());
+        for stat in Stat::ALL {
+            assert_eq!(stats.get(stat), stat.base());
+        }
+    }
+
+    #[test]
+    fn additive_modifiers_apply_before_multiplicative_ones() {
+        let base = BaseStats::default().with(Stat::MoveSpeed, 120.0);
+        let modifiers = StatModifiers(vec![
+            modifier(Stat::MoveSpeed, ModifierKind::Multiplicative, 0.25),
+            modifier(Stat::MoveSpeed, ModifierKind::Additive, 30.0),
+            modifier(Stat::MoveSpeed, ModifierKind::Multiplicative, 0.25),
+            modifier(Stat::MaxHealth, ModifierKind::Additive, 50.0),
+        ]);
+
+        let stats = Stats::new(&base, &modifiers);
+        assert_eq!(stats.get(Stat::MoveSpeed), (120.0 + 30.0) * 1.5);
+        assert_eq!(stats.get(Stat::MaxHealth), 150.0);
+        assert_eq!(stats.get(Stat::Damage), 1.0);
+    }
+
+    #[test]
+    fn stats_never_go_negative() {
+        let modifiers = StatModifiers(vec![modifier(
+            Stat::Cooldown,
+            ModifierKind::Multiplicative,
+            -1.5,
+        )]);
+
+        let stats = Stats::new(&BaseStats::default(), &modifiers);
+        assert_eq!(stats.get(Stat::Cooldown), 0.0);
+    }
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy_rand::prelude::{ChaCha8Rng, GlobalEntropy};
+
+use crate::{
+    campfire::CampfireSnapshot,
+    character::SelectedCharacter,
+    enemy::{EnemySnapshot, WaveSnapshot},
+    experience::GemSnapshot,
+    player::PlayerSnapshot,
+    resources::Resources,
+    run::{RunSeed, RunStats},
+    save::SaveDir,
+    GameState,
+};
+
+pub const SUSPENDED_RUN_FILE: &str = "run.ron";
+/// Bumped whenever `SuspendedRun` changes shape, older saves are discarded instead of resumed.
+pub const SUSPENDED_RUN_VERSION: u32 = 1;
+
+pub struct SuspendPlugin;
+
+impl Plugin for SuspendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SuspendRun>()
+            .add_event::<ResumeRun>()
+            .init_resource::<SuspendedRun>()
+            .configure_sets(
+                Update,
+                (SuspendSystems::Capture, SuspendSystems::Write)
+                    .chain()
+                    .run_if(in_state(GameState::Paused).and_then(on_event::<SuspendRun>())),
+            )
+            .configure_sets(
+                FixedFirst,
+                SuspendSystems::Restore.run_if(resource_exists::<ResumedRun>),
+            )
+            .add_systems(Update, write_suspended_run.in_set(SuspendSystems::Write))
+            .add_systems(
+                Update,
+                resume_run.run_if(in_state(GameState::MainMenu).and_then(on_event::<ResumeRun>())),
+            )
+            .add_systems(
+                FixedFirst,
+                finish_resume
+                    .after(SuspendSystems::Restore)
+                    .run_if(resource_exists::<ResumedRun>),
+            );
+    }
+}
+
+/// Every module owning a piece of the run copies it into `SuspendedRun` during `Capture` and
+/// reads it back from `ResumedRun` during `Restore`, right after the run was set up as new.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SuspendSystems {
+    Capture,
+    Write,
+    Restore,
+}
+
+#[derive(Event, Debug)]
+pub struct SuspendRun;
+
+#[derive(Event, Debug)]
+pub struct ResumeRun;
+
+#[derive(Resource, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SuspendedRun {
+    pub version: u32,
+    pub seed: u64,
+    pub character: String,
+    pub stats: RunStats,
+    pub rng: Option<GlobalEntropy<ChaCha8Rng>>,
+    pub rerolls: u32,
+    pub wave: WaveSnapshot,
+    pub player: PlayerSnapshot,
+    pub enemies: Vec<EnemySnapshot>,
+    pub gems: Vec<GemSnapshot>,
+    #[serde(default)]
+    pub campfires: CampfireSnapshot,
+    /// `Resources` and the resources lying on the ground, stored as a reflected scene.
+    pub scene: String,
+}
+
+/// Suspended run being restored, present from leaving the main menu until the first fixed tick
+/// of the resumed run.
+#[derive(Resource, Debug, Deref)]
+pub struct ResumedRun(pub SuspendedRun);
+
+fn write_suspended_run(
+    mut suspended: ResMut<SuspendedRun>,
+    mut resources: ResMut<Resources>,
+    save_dir: Res<SaveDir>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let mut suspended = std::mem::take(&mut *suspended);
+    suspended.version = SUSPENDED_RUN_VERSION;
+    save_dir.save(SUSPENDED_RUN_FILE, &suspended);
+
+    // Banked once the resumed run ends instead of now, they're part of the save
+    *resources = Resources::default();
+    next_state.set(GameState::MainMenu);
+}
+
+fn resume_run(
+    mut commands: Commands,
+    save_dir: Res<SaveDir>,
+    mut seed: ResMut<RunSeed>,
+    mut selected: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(suspended) = load_suspended_run(&save_dir) else {
+        return error!("No suspended run to resume");
+    };
+    save_dir.remove(SUSPENDED_RUN_FILE);
+
+    seed.0 = suspended.seed;
+    selected.0 = suspended.character.clone();
+    commands.insert_resource(ResumedRun(suspended));
+    next_state.set(GameState::Playing);
+}
+
+/// The suspended run, unless there's none or it was saved by another version. Such a save is
+/// left in place.
+pub fn load_suspended_run(save_dir: &SaveDir) -> Option<SuspendedRun> {
+    let suspended = save_dir.load::<SuspendedRun>(SUSPENDED_RUN_FILE)?;
+    if suspended.version != SUSPENDED_RUN_VERSION {
+        warn!(
+            "Ignoring suspended run saved with version {}, expected {}",
+            suspended.version, SUSPENDED_RUN_VERSION
+        );
+        return None;
+    }
+
+    Some(suspended)
+}
+
+fn finish_resume(mut commands: Commands) {
+    commands.remove_resource::<ResumedRun>();
+}
@@ -0,0 +1,291 @@
+use avian2d::prelude::{Collider, PhysicsSet};
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    sprite::Anchor,
+    utils::hashbrown::HashMap,
+};
+use bevy_rand::prelude::ChaCha8Rng;
+use rand::{Rng, SeedableRng};
+
+use crate::{
+    assets::{ConfigHandles, TerrainConfig},
+    enemy::Enemy,
+    player::Player,
+    run::{InRun, RunSeed},
+    GameState,
+};
+
+/// Depth of the ground, props are drawn just above it and everything else above those.
+const GROUND_Z: f32 = -10.0;
+/// The player and enemies are kept this far out of obstacles, roughly half their width.
+pub(crate) const BODY_RADIUS: f32 = 12.0;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadedChunks>()
+            .add_systems(OnEnter(InRun), reset_loaded_chunks)
+            .add_systems(
+                FixedUpdate,
+                stream_chunks
+                    .run_if(in_state(GameState::Playing).and_then(any_with_component::<Player>)),
+            )
+            // After everything moved for the tick, before physics looks for collisions
+            .add_systems(
+                FixedPostUpdate,
+                push_out_of_obstacles
+                    .before(PhysicsSet::Prepare)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Every spawned chunk by coordinate, along with the obstacles it spawned.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct LoadedChunks {
+    chunks: HashMap<IVec2, LoadedChunk>,
+    chunk_size: f32,
+    largest_radius: f32,
+}
+
+#[derive(Debug)]
+struct LoadedChunk {
+    entity: Entity,
+    obstacles: Vec<Obstacle>,
+}
+
+impl LoadedChunks {
+    /// Obstacles of every chunk within `distance` of `position`, padded by the largest obstacle
+    /// radius, so any obstacle whose edge is closer than `distance` is among them.
+    pub(crate) fn obstacles_near(
+        &self,
+        position: Vec2,
+        distance: f32,
+    ) -> impl Iterator<Item = &Obstacle> {
+        let reach = Vec2::splat(distance + self.largest_radius);
+        let chunk_size = self.chunk_size.max(f32::EPSILON);
+        let min = ((position - reach) / chunk_size).floor().as_ivec2();
+        let max = ((position + reach) / chunk_size).floor().as_ivec2();
+
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(move |coordinate| self.chunks.get(&coordinate))
+            .flat_map(|chunk| &chunk.obstacles)
+    }
+}
+
+#[derive(Component)]
+struct Chunk;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Obstacle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+fn reset_loaded_chunks(mut chunks: ResMut<LoadedChunks>) {
+    *chunks = LoadedChunks::default();
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<LoadedChunks>,
+    mut images: ResMut<Assets<Image>>,
+    player: Query<&Transform, With<Player>>,
+    config_handles: Res<ConfigHandles>,
+    terrain: Res<Assets<TerrainConfig>>,
+    seed: Res<RunSeed>,
+) {
+    let Some(terrain) = terrain.get(config_handles.terrain.id()) else {
+        return error!("Terrain config should be loaded at this point");
+    };
+    let Ok(transform) = player.get_single() else {
+        return;
+    };
+
+    let chunk_size = terrain.tile_size * terrain.chunk_tiles as f32;
+    let center = (transform.translation.truncate() / chunk_size)
+        .floor()
+        .as_ivec2();
+    chunks.chunk_size = chunk_size;
+
+    chunks.chunks.retain(|coordinate, chunk| {
+        let keep = (*coordinate - center).abs().max_element() <= terrain.unload_radius;
+        if !keep {
+            commands.entity(chunk.entity).despawn_recursive();
+        }
+        keep
+    });
+
+    for y in -terrain.load_radius..=terrain.load_radius {
+        for x in -terrain.load_radius..=terrain.load_radius {
+            let coordinate = center + IVec2::new(x, y);
+            if !chunks.chunks.contains_key(&coordinate) {
+                let chunk = spawn_chunk(&mut commands, &mut images, terrain, seed.0, coordinate);
+                chunks.largest_radius = chunk
+                    .obstacles
+                    .iter()
+                    .fold(chunks.largest_radius, |largest, obstacle| {
+                        largest.max(obstacle.radius)
+                    });
+                chunks.chunks.insert(coordinate, chunk);
+            }
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    terrain: &TerrainConfig,
+    seed: u64,
+    coordinate: IVec2,
+) -> LoadedChunk {
+    let chunk_size = terrain.tile_size * terrain.chunk_tiles as f32;
+    let origin = coordinate.as_vec2() * chunk_size;
+    let mut rng = ChaCha8Rng::seed_from_u64(hash(seed, coordinate.x, coordinate.y));
+    let mut obstacles = Vec::new();
+
+    let entity = commands
+        .spawn((
+            Name::from(format!("Chunk ({}, {})", coordinate.x, coordinate.y)),
+            Chunk,
+            SpriteBundle {
+                texture: images.add(ground_image(terrain, seed, coordinate)),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(chunk_size)),
+                    anchor: Anchor::BottomLeft,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(origin.extend(GROUND_Z)),
+                ..Default::default()
+            },
+            StateScoped(InRun),
+        ))
+        .with_children(|parent| {
+            for prop in &terrain.props {
+                let extra = rng.gen_bool(prop.per_chunk.fract().clamp(0.0, 1.0) as f64);
+                let count = prop.per_chunk as u32 + extra as u32;
+
+                for _ in 0..count {
+                    let offset = Vec2::new(
+                        rng.gen_range(0.0..chunk_size),
+                        rng.gen_range(0.0..chunk_size),
+                    );
+                    let center = origin + offset;
+                    if prop.obstacle && center.length() < terrain.clear_radius {
+                        continue;
+                    }
+
+                    let size = Vec2::new(prop.size.0, prop.size.1);
+                    let mut entity = parent.spawn((
+                        Name::from(if prop.obstacle { "Obstacle" } else { "Prop" }),
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::srgb(prop.color.0, prop.color.1, prop.color.2),
+                                custom_size: Some(size),
+                                ..Default::default()
+                            },
+                            transform: Transform::from_translation(
+                                offset.extend(if prop.obstacle { 2.0 } else { 1.0 }),
+                            ),
+                            ..Default::default()
+                        },
+                    ));
+
+                    if prop.obstacle {
+                        let radius = size.max_element() / 2.0;
+                        entity.insert(Collider::circle(radius));
+                        obstacles.push(Obstacle { center, radius });
+                    }
+                }
+            }
+        })
+        .id();
+
+    LoadedChunk { entity, obstacles }
+}
+
+/// One pixel per tile, stretched over the chunk without filtering.
+fn ground_image(terrain: &TerrainConfig, seed: u64, coordinate: IVec2) -> Image {
+    let tiles = terrain.chunk_tiles;
+    let mut data = Vec::with_capacity((tiles * tiles * 4) as usize);
+
+    // Image rows go top to bottom, tiles bottom to top
+    for row in (0..tiles).rev() {
+        for column in 0..tiles {
+            let tile = coordinate * tiles as i32 + IVec2::new(column as i32, row as i32);
+            let noise = value_noise(seed, tile.as_vec2() / terrain.patch_size.max(1.0));
+            let index = ((noise * terrain.ground.len() as f32) as usize)
+                .min(terrain.ground.len().saturating_sub(1));
+            let (red, green, blue) = terrain.ground.get(index).copied().unwrap_or_default();
+            data.extend(Color::srgb(red, green, blue).to_srgba().to_u8_array());
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: tiles,
+            height: tiles,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn hash(seed: u64, x: i32, y: i32) -> u64 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+    hash
+}
+
+/// Smooth noise in `0.0..1.0`, interpolated between random values on the integer lattice.
+fn value_noise(seed: u64, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let t = point - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let lattice = |x, y| (hash(seed, x, y) >> 40) as f32 / (1u64 << 24) as f32;
+    let lerp = |from: f32, to: f32, t: f32| from + (to - from) * t;
+
+    let bottom = lerp(lattice(x, y), lattice(x + 1, y), t.x);
+    let top = lerp(lattice(x, y + 1), lattice(x + 1, y + 1), t.x);
+    lerp(bottom, top, t.y)
+}
+
+fn push_out_of_obstacles(
+    chunks: Res<LoadedChunks>,
+    mut bodies: Query<&mut Transform, Or<(With<Player>, With<Enemy>)>>,
+) {
+    for mut transform in &mut bodies {
+        let mut position = transform.translation.truncate();
+        for obstacle in chunks.obstacles_near(position, BODY_RADIUS) {
+            let offset = position - obstacle.center;
+            let distance = obstacle.radius + BODY_RADIUS;
+            if offset.length_squared() >= distance * distance {
+                continue;
+            }
+
+            position = obstacle.center + offset.try_normalize().unwrap_or(Vec2::X) * distance;
+        }
+
+        if position != transform.translation.truncate() {
+            transform.translation = position.extend(transform.translation.z);
+        }
+    }
+}
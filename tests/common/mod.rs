@@ -0,0 +1,195 @@
+use std::{fs, path::Path, time::Duration};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput, NativeKey},
+        ButtonState, InputPlugin,
+    },
+    prelude::*,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    utils::hashbrown::HashMap,
+    window::PrimaryWindow,
+};
+use bevy_hanabi::EffectAsset;
+use noname::{
+    assets::{
+        AnimationsConfig, CampfireConfig, CharactersConfig, ConfigHandles, DropsConfig,
+        EnemiesConfig, GameAssetsHandles, ProgressionConfig, ShopConfig, TerrainConfig,
+        WavesConfig,
+    },
+    common::Health,
+    enemy::{Enemy, Formation, SpawnEnemies},
+    player::{Player, Weapon},
+    GameState, GameplayPlugin,
+};
+use serde::de::DeserializeOwned;
+
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// Builds the game on top of `MinimalPlugins`, with configs read straight from `assets/` and
+/// placeholder handles for every sprite sheet.
+pub fn headless_app() -> App {
+    headless_app_with(GameplayPlugin::default())
+}
+
+pub fn headless_app_with(gameplay: GameplayPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        StatesPlugin,
+    ))
+    .init_asset::<Image>()
+    .init_asset::<TextureAtlasLayout>()
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<EffectAsset>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+    .add_plugins(gameplay);
+
+    app.world_mut().spawn((Window::default(), PrimaryWindow));
+
+    let config_handles = ConfigHandles {
+        animations: load_config::<AnimationsConfig>(&mut app, "config.animations.ron"),
+        enemies: load_config::<EnemiesConfig>(&mut app, "config.enemies.ron"),
+        waves: load_config::<WavesConfig>(&mut app, "config.waves.ron"),
+        progression: load_config::<ProgressionConfig>(&mut app, "config.progression.ron"),
+        characters: load_config::<CharactersConfig>(&mut app, "config.characters.ron"),
+        shop: load_config::<ShopConfig>(&mut app, "config.shop.ron"),
+        drops: load_config::<DropsConfig>(&mut app, "config.drops.ron"),
+        campfires: load_config::<CampfireConfig>(&mut app, "config.campfires.ron"),
+        terrain: load_config::<TerrainConfig>(&mut app, "config.terrain.ron"),
+    };
+
+    let monsters_sheets = app
+        .world()
+        .resource::<Assets<EnemiesConfig>>()
+        .get(config_handles.enemies.id())
+        .expect("enemies config was just added")
+        .0
+        .values()
+        .map(|archetype| (archetype.sheet.clone(), Handle::default()))
+        .collect::<HashMap<_, _>>();
+
+    app.insert_resource(config_handles)
+        .insert_resource(GameAssetsHandles {
+            characters_layouts: Handle::default(),
+            characters_sheets: HashMap::from([("cleric".to_string(), Handle::default())]),
+            portraits: Handle::default(),
+            portraits_layout: Handle::default(),
+            halfling_layout: Handle::default(),
+            skeleton_layout: Handle::default(),
+            monk_layout: Handle::default(),
+            monsters_sheets,
+            resources: Handle::default(),
+            resources_layout: Handle::default(),
+        });
+
+    app
+}
+
+fn load_config<A: Asset + DeserializeOwned>(app: &mut App, file: &str) -> Handle<A> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(file);
+    let config = fs::read_to_string(&path).expect("config file should exist");
+    let config = ron::from_str::<A>(&config).expect("config file should be valid");
+    app.world_mut().resource_mut::<Assets<A>>().add(config)
+}
+
+/// Skips asset loading and the main menu, leaves the app in the first frame of a run.
+pub fn start_run(app: &mut App) {
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    for _ in 0..10 {
+        app.update();
+        if *app.world().resource::<State<GameState>>().get() == GameState::Playing {
+            return;
+        }
+    }
+
+    panic!("run didn't start");
+}
+
+pub fn step(app: &mut App, frames: u32) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+pub fn press(app: &mut App, key: KeyCode) {
+    send_key(app, key, ButtonState::Pressed);
+}
+
+pub fn release(app: &mut App, key: KeyCode) {
+    send_key(app, key, ButtonState::Released);
+}
+
+fn send_key(app: &mut App, key_code: KeyCode, state: ButtonState) {
+    app.world_mut().send_event(KeyboardInput {
+        key_code,
+        logical_key: Key::Unidentified(NativeKey::Unidentified),
+        state,
+        window: Entity::PLACEHOLDER,
+    });
+}
+
+pub fn spawn_enemies(app: &mut App, archetype: &str, count: u32, radius: f32) {
+    app.world_mut().send_event(SpawnEnemies {
+        archetype: archetype.to_string(),
+        count,
+        formation: Formation::Ring { radius },
+        elite: false,
+        health_multiplier: 1.0,
+    });
+}
+
+pub fn spawn_elite(app: &mut App, archetype: &str, radius: f32) {
+    app.world_mut().send_event(SpawnEnemies {
+        archetype: archetype.to_string(),
+        count: 1,
+        formation: Formation::Ring { radius },
+        elite: true,
+        health_multiplier: 2.0,
+    });
+}
+
+pub fn enemies(app: &mut App) -> Vec<(Entity, u32)> {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(Entity, &Health), With<Enemy>>();
+    query
+        .iter(app.world())
+        .map(|(entity, health)| (entity, health.0))
+        .collect()
+}
+
+/// Position and health of the player.
+pub fn player(app: &mut App) -> (Vec3, u32) {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(&Transform, &Health), With<Player>>();
+    let (transform, health) = query.single(app.world());
+    (transform.translation, health.0)
+}
+
+pub fn set_player_health(app: &mut App, health: u32) {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<&mut Health, With<Player>>();
+    query.single_mut(app.world_mut()).0 = health;
+}
+
+/// Removes every weapon of the player, so nothing kills the enemies.
+pub fn disarm_player(app: &mut App) {
+    let mut weapons = app.world_mut().query_filtered::<Entity, With<Weapon>>();
+    for weapon in weapons.iter(app.world()).collect::<Vec<_>>() {
+        app.world_mut().entity_mut(weapon).despawn_recursive();
+    }
+}
@@ -0,0 +1,688 @@
+mod common;
+
+use bevy::prelude::*;
+use noname::{
+    animation::{AnimationController, AnimationState},
+    assets::{
+        CampfireConfig, ChestRewards, ConfigHandles, ProgressionConfig, TerrainConfig, WavesConfig,
+    },
+    enemy::{Dying, Enemy},
+    input::{InputSource, Replay},
+    meta::Bank,
+    player::{MovementDirection, Player},
+    resources::{Loot, Resources},
+    run::{RunSeed, RunStats},
+    save::SaveDir,
+    suspend::{ResumeRun, SuspendRun, SuspendedRun, SUSPENDED_RUN_FILE},
+    GameState, GameplayPlugin,
+};
+
+use common::{
+    disarm_player, enemies, headless_app, headless_app_with, player, press, release,
+    set_player_health, spawn_elite, spawn_enemies, start_run, step,
+};
+
+#[test]
+fn first_wave_spawns_enemies() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    assert!(enemies(&mut app).is_empty());
+
+    step(&mut app, 120);
+
+    assert!(!enemies(&mut app).is_empty());
+}
+
+#[test]
+fn player_moves_with_input() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    let (start, _) = player(&mut app);
+
+    press(&mut app, KeyCode::KeyD);
+    step(&mut app, 30);
+    release(&mut app, KeyCode::KeyD);
+    step(&mut app, 1);
+    let (moved, _) = player(&mut app);
+
+    assert!(moved.x > start.x);
+    assert_eq!(moved.y, start.y);
+}
+
+#[test]
+fn player_animation_follows_movement() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    assert_eq!(player_animation(&mut app), AnimationState::Idle);
+
+    press(&mut app, KeyCode::KeyW);
+    step(&mut app, 5);
+    assert_eq!(
+        player_animation(&mut app),
+        AnimationState::Run(MovementDirection::Up)
+    );
+
+    release(&mut app, KeyCode::KeyW);
+    step(&mut app, 5);
+    assert_eq!(player_animation(&mut app), AnimationState::Idle);
+}
+
+fn player_animation(app: &mut App) -> AnimationState {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<&AnimationController, With<Player>>();
+    query.single(app.world()).state()
+}
+
+#[test]
+fn orb_damages_enemies_in_its_path() {
+    let mut app = headless_app();
+    start_run(&mut app);
+
+    // Right on the orb's orbit, so it sweeps through them within a few frames
+    spawn_enemies(&mut app, "monk", 12, 70.0);
+    step(&mut app, 1);
+    let spawned = enemies(&mut app);
+    let full_health = spawned[0].1;
+
+    step(&mut app, 20);
+
+    let damaged = enemies(&mut app)
+        .into_iter()
+        .filter(|(entity, health)| {
+            spawned.iter().any(|(spawned, _)| spawned == entity) && *health < full_health
+        })
+        .count();
+    assert!(damaged > 0);
+}
+
+#[test]
+fn enemies_spread_out_around_the_player() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    // Nothing kills the crowd and the crowd doesn't kill the player
+    disarm_player(&mut app);
+    set_player_health(&mut app, 10_000);
+
+    spawn_enemies(&mut app, "halfling", 12, 150.0);
+    step(&mut app, 600);
+
+    let mut transforms = app
+        .world_mut()
+        .query_filtered::<&Transform, (With<Enemy>, Without<Dying>)>();
+    let positions = transforms
+        .iter(app.world())
+        .map(|transform| transform.translation.truncate())
+        .collect::<Vec<_>>();
+    assert!(positions.len() >= 12);
+    for (index, position) in positions.iter().enumerate() {
+        for other in &positions[index + 1..] {
+            assert!(position.distance(*other) > 4.0);
+        }
+    }
+}
+
+#[test]
+fn enemies_stop_at_their_stop_distance() {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().waves.clone();
+    let mut waves = app.world_mut().resource_mut::<Assets<WavesConfig>>();
+    let waves = waves.get_mut(&handle).unwrap();
+    // Only the enemy spawned below, with no crowd pushing it around
+    waves.waves.clear();
+    waves.events.clear();
+    start_run(&mut app);
+    disarm_player(&mut app);
+    set_player_health(&mut app, 10_000);
+
+    spawn_enemies(&mut app, "monk", 1, 150.0);
+    step(&mut app, 600);
+
+    let (player_position, _) = player(&mut app);
+    let mut transforms = app.world_mut().query_filtered::<&Transform, With<Enemy>>();
+    let distance = transforms
+        .single(app.world())
+        .translation
+        .truncate()
+        .distance(player_position.truncate());
+    // The monk's stop distance, give or take a step
+    assert!((14.0..=18.0).contains(&distance), "stopped {distance} away");
+}
+
+#[test]
+fn enemies_hurt_the_player_on_contact() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    let (_, full_health) = player(&mut app);
+
+    // Inside the orb's orbit, so they reach the player before it kills them
+    spawn_enemies(&mut app, "monk", 4, 25.0);
+    step(&mut app, 60);
+
+    let (_, health) = player(&mut app);
+    assert!(health < full_health);
+}
+
+#[test]
+fn dying_enemies_despawn_once_death_animation_ends() {
+    let mut app = headless_app();
+    start_run(&mut app);
+
+    spawn_enemies(&mut app, "monk", 5, 300.0);
+    step(&mut app, 1);
+    let dying = enemies(&mut app);
+    assert_eq!(dying.len(), 5);
+
+    for (entity, _) in &dying {
+        app.world_mut().entity_mut(*entity).insert(Dying);
+    }
+    step(&mut app, 180);
+
+    assert!(dying
+        .iter()
+        .all(|(entity, _)| app.world().get_entity(*entity).is_none()));
+}
+
+#[test]
+fn killed_enemies_drop_resources_the_player_collects() {
+    let mut app = headless_app();
+    start_run(&mut app);
+    assert_eq!(app.world().resource::<Resources>().total(), 0);
+
+    // Within pickup range, so the drops get pulled in right away
+    spawn_enemies(&mut app, "monk", 3, 40.0);
+    step(&mut app, 1);
+    for (entity, _) in enemies(&mut app) {
+        app.world_mut().entity_mut(entity).insert(Dying);
+    }
+    step(&mut app, 300);
+
+    assert!(app.world().resource::<Resources>().total() > 0);
+}
+
+#[test]
+fn killed_elites_drop_a_chest() {
+    let mut app = headless_app();
+    start_run(&mut app);
+
+    spawn_elite(&mut app, "monk", 300.0);
+    step(&mut app, 1);
+    for (entity, _) in enemies(&mut app) {
+        app.world_mut().entity_mut(entity).insert(Dying);
+    }
+    step(&mut app, 180);
+
+    let mut loot = app.world_mut().query::<&Loot>();
+    let chests = loot
+        .iter(app.world())
+        .filter(|loot| matches!(loot, Loot::Chest(_)))
+        .count();
+    assert_eq!(chests, 1);
+}
+
+#[test]
+fn chests_reveal_drops_from_their_table() {
+    let lines = open_elite_chest(ChestRewards {
+        upgrade_chance: 0.0,
+        upgrade_counts: vec![1],
+    });
+
+    // The guaranteed gold, then one weighted pick of the "chest" table
+    assert_eq!(lines.len(), 2);
+    let (gold, resource) = lines[0].split_once(' ').unwrap();
+    assert!((5..=10).contains(&gold.parse::<u32>().unwrap()));
+    assert_eq!(resource, "Gold");
+    let (amount, resource) = lines[1].split_once(' ').unwrap();
+    let range = match resource {
+        "Crystals" | "Gems" => 2..=4,
+        "Mercury" => 3..=6,
+        other => panic!("{other} isn't in the chest table"),
+    };
+    assert!(range.contains(&amount.parse::<u32>().unwrap()));
+}
+
+#[test]
+fn chests_can_reveal_several_upgrades() {
+    let lines = open_elite_chest(ChestRewards {
+        upgrade_chance: 1.0,
+        upgrade_counts: vec![0, 0, 1],
+    });
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| !line.is_empty()));
+    assert!(lines[0] != lines[1] && lines[1] != lines[2] && lines[0] != lines[2]);
+}
+
+/// Kills an elite next to the player, then returns the reward its chest reveals once play
+/// resumes.
+fn open_elite_chest(rewards: ChestRewards) -> Vec<String> {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().progression.clone();
+    let mut progression = app.world_mut().resource_mut::<Assets<ProgressionConfig>>();
+    progression.get_mut(&handle).unwrap().chest = rewards;
+    start_run(&mut app);
+
+    // Within pickup range, so the chest gets pulled in right away
+    spawn_elite(&mut app, "monk", 40.0);
+    step(&mut app, 1);
+    for (entity, _) in enemies(&mut app) {
+        app.world_mut().entity_mut(entity).insert(Dying);
+    }
+
+    let mut revealed = None;
+    for _ in 0..600 {
+        step(&mut app, 1);
+        let state = app.world().resource::<State<GameState>>().get().clone();
+        if state == GameState::Chest {
+            revealed = chest_reveal_text(&mut app);
+        } else if let Some(text) = revealed {
+            assert_eq!(state, GameState::Playing);
+            return text.lines().map(str::to_string).collect();
+        }
+    }
+
+    panic!("chest was never revealed, or play didn't resume");
+}
+
+/// Text of the roulette, the last line of the reveal screen.
+fn chest_reveal_text(app: &mut App) -> Option<String> {
+    let mut screens = app.world_mut().query::<(&Name, &Children)>();
+    let roulette = *screens
+        .iter(app.world())
+        .find(|(name, _)| name.as_str() == "Chest reveal")?
+        .1
+        .last()?;
+    let text = app.world().get::<Text>(roulette)?;
+    Some(text.sections[0].value.clone())
+}
+
+#[test]
+fn resting_at_a_campfire_heals_then_opens_its_shop() {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().campfires.clone();
+    let mut campfires = app.world_mut().resource_mut::<Assets<CampfireConfig>>();
+    let config = campfires.get_mut(&handle).unwrap();
+    // Right on top of the player, before the first wave gets close
+    config.interval = 0.2;
+    config.distance = (0.0, 0.0);
+    config.rest_time = 1.0;
+    start_run(&mut app);
+
+    set_player_health(&mut app, 10);
+
+    for _ in 0..180 {
+        step(&mut app, 1);
+        if *app.world().resource::<State<GameState>>().get() == GameState::Campfire {
+            break;
+        }
+    }
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::Campfire
+    );
+    let (_, healed) = player(&mut app);
+    assert!(healed > 10);
+
+    press(&mut app, KeyCode::Escape);
+    step(&mut app, 2);
+    release(&mut app, KeyCode::Escape);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::Playing
+    );
+}
+
+#[test]
+fn unused_campfires_stop_piling_up() {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().campfires.clone();
+    let mut campfires = app.world_mut().resource_mut::<Assets<CampfireConfig>>();
+    let config = campfires.get_mut(&handle).unwrap();
+    // Out of reach, so none of them gets used
+    config.interval = 0.2;
+    config.distance = (300.0, 300.0);
+    config.max_active = 2;
+    start_run(&mut app);
+
+    step(&mut app, 120);
+
+    let mut names = app.world_mut().query::<&Name>();
+    let count = names
+        .iter(app.world())
+        .filter(|name| name.as_str() == "Campfire")
+        .count();
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn terrain_chunks_stream_around_the_player() {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().terrain.clone();
+    let mut terrain = app.world_mut().resource_mut::<Assets<TerrainConfig>>();
+    // Nothing in the way of walking straight ahead
+    terrain
+        .get_mut(&handle)
+        .unwrap()
+        .props
+        .retain(|prop| !prop.obstacle);
+    start_run(&mut app);
+    // Outlives whatever the first waves throw at it
+    set_player_health(&mut app, 10_000);
+    step(&mut app, 1);
+    let around_start = chunks(&mut app);
+    assert_eq!(around_start.len(), 25);
+
+    // Far past the unload radius, the loaded chunks never outgrow it
+    press(&mut app, KeyCode::KeyD);
+    for _ in 0..30 {
+        step(&mut app, 100);
+        let loaded = chunks(&mut app).len();
+        assert!((25..=49).contains(&loaded));
+    }
+    release(&mut app, KeyCode::KeyD);
+    step(&mut app, 1);
+
+    let streamed = chunks(&mut app);
+    let (position, _) = player(&mut app);
+    assert!(position.x > 2500.0);
+    assert!(streamed.iter().any(|name| !around_start.contains(name)));
+    assert!(around_start.iter().any(|name| !streamed.contains(name)));
+}
+
+fn chunks(app: &mut App) -> Vec<String> {
+    let mut names = app.world_mut().query::<&Name>();
+    names
+        .iter(app.world())
+        .filter(|name| name.starts_with("Chunk"))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[test]
+fn shop_upgrades_apply_to_the_next_run() {
+    let mut app = headless_app();
+    app.world_mut()
+        .resource_mut::<Bank>()
+        .upgrades
+        .insert("vitality".to_string(), 2);
+
+    start_run(&mut app);
+    step(&mut app, 1);
+
+    let (_, health) = player(&mut app);
+    assert_eq!(health, 120);
+}
+
+#[test]
+fn suspended_run_resumes_where_it_was_left() {
+    let mut app = headless_app();
+    let save_dir = SaveDir(Some(
+        std::env::temp_dir().join(format!("noname-suspend-{}", std::process::id())),
+    ));
+    app.insert_resource(save_dir.clone());
+    start_run(&mut app);
+
+    press(&mut app, KeyCode::KeyD);
+    step(&mut app, 30);
+    release(&mut app, KeyCode::KeyD);
+    spawn_enemies(&mut app, "monk", 3, 300.0);
+    step(&mut app, 1);
+
+    press(&mut app, KeyCode::Escape);
+    step(&mut app, 2);
+    release(&mut app, KeyCode::Escape);
+    let (position, health) = player(&mut app);
+    let enemy_count = enemies(&mut app).len();
+
+    app.world_mut().send_event(SuspendRun);
+    step(&mut app, 3);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::MainMenu
+    );
+    assert!(save_dir.exists(SUSPENDED_RUN_FILE));
+
+    app.world_mut().send_event(ResumeRun);
+    step(&mut app, 3);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::Playing
+    );
+    assert!(!save_dir.exists(SUSPENDED_RUN_FILE));
+
+    let (resumed_position, resumed_health) = player(&mut app);
+    assert!(resumed_position.distance(position) < 5.0);
+    assert_eq!(resumed_health, health);
+    assert_eq!(enemies(&mut app).len(), enemy_count);
+
+    let _ = std::fs::remove_dir_all(save_dir.0.unwrap());
+}
+
+#[test]
+fn outdated_suspended_run_is_kept_but_not_resumed() {
+    let mut app = headless_app();
+    let save_dir = SaveDir(Some(
+        std::env::temp_dir().join(format!("noname-outdated-{}", std::process::id())),
+    ));
+    save_dir.save(SUSPENDED_RUN_FILE, &SuspendedRun::default());
+    app.insert_resource(save_dir.clone());
+
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::MainMenu);
+    step(&mut app, 3);
+    app.world_mut().send_event(ResumeRun);
+    step(&mut app, 3);
+
+    assert_eq!(
+        *app.world().resource::<State<GameState>>().get(),
+        GameState::MainMenu
+    );
+    assert!(save_dir.exists(SUSPENDED_RUN_FILE));
+
+    let _ = std::fs::remove_dir_all(save_dir.0.unwrap());
+}
+
+#[test]
+fn played_back_replay_ends_like_its_recording() {
+    let path = std::env::temp_dir().join(format!("noname-replay-{}.ron", std::process::id()));
+    let mut recording = headless_app_with(GameplayPlugin {
+        input_source: InputSource::Record {
+            path: path.clone(),
+            replay: Replay::default(),
+        },
+        ..Default::default()
+    });
+    recording.insert_resource(RunSeed(7));
+    start_run(&mut recording);
+    set_player_health(&mut recording, 5);
+
+    // Walks in a square, taking the first upgrade of every level up and killing everything once
+    let mut held = KeyCode::KeyD;
+    for frame in 0..18_000 {
+        match *recording.world().resource::<State<GameState>>().get() {
+            GameState::GameOver => break,
+            GameState::LevelUp => {
+                press(&mut recording, KeyCode::Digit1);
+                step(&mut recording, 1);
+                release(&mut recording, KeyCode::Digit1);
+                continue;
+            }
+            _ => {}
+        }
+
+        let key = [KeyCode::KeyD, KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS][frame / 90 % 4];
+        if key != held {
+            release(&mut recording, held);
+            held = key;
+        }
+        press(&mut recording, key);
+        match frame {
+            300 => press(&mut recording, KeyCode::Space),
+            301 => release(&mut recording, KeyCode::Space),
+            _ => {}
+        }
+        step(&mut recording, 1);
+    }
+    assert_eq!(
+        *recording.world().resource::<State<GameState>>().get(),
+        GameState::GameOver
+    );
+
+    let replay = ron::from_str::<Replay>(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let death_tick = replay.ticks.len();
+    assert!(!replay.kill_all.is_empty());
+
+    let mut playback = headless_app_with(GameplayPlugin {
+        input_source: InputSource::Playback {
+            replay,
+            tick: 0,
+            upgrade: 0,
+            campfire: 0,
+        },
+        ..Default::default()
+    });
+    playback.insert_resource(RunSeed(7));
+    start_run(&mut playback);
+    set_player_health(&mut playback, 5);
+
+    for _ in 0..18_000 {
+        if *playback.world().resource::<State<GameState>>().get() == GameState::GameOver {
+            break;
+        }
+        step(&mut playback, 1);
+    }
+    assert_eq!(
+        *playback.world().resource::<State<GameState>>().get(),
+        GameState::GameOver
+    );
+
+    let InputSource::Playback { tick, .. } = playback.world().resource::<InputSource>() else {
+        panic!("input source changed during playback");
+    };
+    assert_eq!(*tick, death_tick);
+
+    let (recorded, played) = (
+        recording.world().resource::<RunStats>(),
+        playback.world().resource::<RunStats>(),
+    );
+    assert_eq!(played.kills, recorded.kills);
+    assert_eq!(played.elapsed, recorded.elapsed);
+    assert_eq!(
+        format!("{:?}", playback.world().resource::<Resources>()),
+        format!("{:?}", recording.world().resource::<Resources>())
+    );
+}
+
+#[test]
+fn runs_with_the_same_seed_play_out_the_same() {
+    let first = scripted_run(7);
+    let second = scripted_run(7);
+    assert!(!first.enemies.is_empty());
+    assert!(!first.loot.is_empty());
+    assert_eq!(first, second);
+
+    let other = scripted_run(8);
+    assert_ne!(first.enemies, other.enemies);
+}
+
+#[derive(Debug, PartialEq)]
+struct RunSnapshot {
+    enemies: Vec<(String, Vec3)>,
+    loot: Vec<(Loot, Vec3)>,
+    stats: String,
+}
+
+/// Walks in a square for a minute of play, taking the first upgrade of every level up.
+fn scripted_run(seed: u64) -> RunSnapshot {
+    let mut app = headless_app();
+    app.insert_resource(RunSeed(seed));
+    start_run(&mut app);
+    set_player_health(&mut app, 10_000);
+
+    let mut held = KeyCode::KeyD;
+    for frame in 0..3600 {
+        if *app.world().resource::<State<GameState>>().get() == GameState::LevelUp {
+            press(&mut app, KeyCode::Digit1);
+            step(&mut app, 1);
+            release(&mut app, KeyCode::Digit1);
+            continue;
+        }
+
+        let key = [KeyCode::KeyD, KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS][frame / 120 % 4];
+        if key != held {
+            release(&mut app, held);
+            held = key;
+        }
+        press(&mut app, key);
+        step(&mut app, 1);
+    }
+
+    let mut enemies = app
+        .world_mut()
+        .query_filtered::<(&Name, &Transform), With<Enemy>>();
+    let enemies = enemies
+        .iter(app.world())
+        .map(|(name, transform)| (name.to_string(), transform.translation))
+        .collect();
+    let mut loot = app.world_mut().query::<(&Loot, &Transform)>();
+    let loot = loot
+        .iter(app.world())
+        .map(|(loot, transform)| (loot.clone(), transform.translation))
+        .collect();
+
+    RunSnapshot {
+        enemies,
+        loot,
+        stats: format!("{:?}", app.world().resource::<RunStats>()),
+    }
+}
+
+#[test]
+fn terrain_chunks_come_back_the_same() {
+    let mut app = headless_app();
+    let handle = app.world().resource::<ConfigHandles>().terrain.clone();
+    let mut terrain = app.world_mut().resource_mut::<Assets<TerrainConfig>>();
+    terrain
+        .get_mut(&handle)
+        .unwrap()
+        .props
+        .retain(|prop| !prop.obstacle);
+    start_run(&mut app);
+    set_player_health(&mut app, 10_000);
+    step(&mut app, 1);
+    let before = chunk_props(&mut app, "Chunk (0, 0)").expect("start chunk should be loaded");
+    assert!(!before.is_empty());
+
+    press(&mut app, KeyCode::KeyD);
+    step(&mut app, 1500);
+    release(&mut app, KeyCode::KeyD);
+    step(&mut app, 1);
+    assert!(chunk_props(&mut app, "Chunk (0, 0)").is_none());
+
+    press(&mut app, KeyCode::KeyA);
+    step(&mut app, 1500);
+    release(&mut app, KeyCode::KeyA);
+    step(&mut app, 1);
+    let after = chunk_props(&mut app, "Chunk (0, 0)").expect("start chunk should be reloaded");
+
+    assert_eq!(after, before);
+}
+
+fn chunk_props(app: &mut App, chunk: &str) -> Option<Vec<(String, Vec3)>> {
+    let mut chunks = app.world_mut().query::<(&Name, &Children)>();
+    let children = chunks
+        .iter(app.world())
+        .find(|(name, _)| name.as_str() == chunk)?
+        .1
+        .to_vec();
+    let mut props = app.world_mut().query::<(&Name, &Transform)>();
+    Some(
+        children
+            .into_iter()
+            .filter_map(|child| props.get(app.world(), child).ok())
+            .map(|(name, transform)| (name.to_string(), transform.translation))
+            .collect(),
+    )
+}